@@ -0,0 +1,159 @@
+use std::fmt;
+
+use crate::{
+    game::MoveRecord,
+    record::{GameRecord, RecordError, ReplayedPly},
+    solve::Solution,
+};
+
+/// losses in win probability above which a move is flagged
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Thresholds {
+    pub inaccuracy: f64,
+    pub mistake: f64,
+    pub blunder: f64,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            inaccuracy: 0.01,
+            mistake: 0.03,
+            blunder: 0.08,
+        }
+    }
+}
+
+impl Thresholds {
+    pub fn flag(&self, loss: f64) -> Option<Flag> {
+        if loss > self.blunder {
+            Some(Flag::Blunder)
+        } else if loss > self.mistake {
+            Some(Flag::Mistake)
+        } else if loss > self.inaccuracy {
+            Some(Flag::Inaccuracy)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Flag {
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl fmt::Display for Flag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Flag::Inaccuracy => write!(f, "inaccuracy"),
+            Flag::Mistake => write!(f, "mistake"),
+            Flag::Blunder => write!(f, "blunder"),
+        }
+    }
+}
+
+/// the solver's verdict on one ply of a recorded game, with values for the player who moved
+#[derive(Debug, Clone)]
+pub struct Annotation {
+    pub ply: ReplayedPly,
+    /// win probability after the chosen move
+    pub chosen: f64,
+    /// win probability after the best move
+    pub best: f64,
+    pub best_move: MoveRecord,
+    pub loss: f64,
+    /// whether there was only one legal move, which is never flagged
+    pub forced: bool,
+    pub flag: Option<Flag>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnnotateError {
+    Record(RecordError),
+    /// the position before this ply isn't in the solved table
+    Unsolved {
+        ply: usize,
+    },
+}
+
+impl fmt::Display for AnnotateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Record(err) => write!(f, "{err}"),
+            Self::Unsolved { ply } => write!(f, "ply {ply}: the position isn't solved"),
+        }
+    }
+}
+
+impl std::error::Error for AnnotateError {}
+
+/// replays the record and compares every move with the best move for its roll. The solution has
+/// to be solved with the record's rules
+pub fn annotate(
+    solution: &Solution,
+    record: &GameRecord,
+    thresholds: &Thresholds,
+) -> Result<Vec<Annotation>, AnnotateError> {
+    let rules = &record.rules;
+    let replayed = record.replay().map_err(AnnotateError::Record)?;
+    replayed
+        .into_iter()
+        .enumerate()
+        .map(|(i, ply)| {
+            let unsolved = AnnotateError::Unsolved { ply: i + 1 };
+            let moves = solution
+                .ranked_moves(rules, ply.game.relative, ply.roll)
+                .ok_or(unsolved.clone())?;
+            let chosen = moves
+                .iter()
+                .find(|ranked| ranked.mov == ply.mov)
+                .ok_or(unsolved)?
+                .val;
+            let best = &moves[0];
+            let loss = (best.val - chosen).max(0.0);
+            let forced = moves.len() == 1;
+            Ok(Annotation {
+                chosen,
+                best: best.val,
+                best_move: best.mov.clone(),
+                loss,
+                forced,
+                flag: if forced { None } else { thresholds.flag(loss) },
+                ply,
+            })
+        })
+        .collect()
+}
+
+/// one line per ply, with the best move shown when it wasn't played
+pub fn format_annotations(annotations: &[Annotation]) -> String {
+    annotations
+        .iter()
+        .enumerate()
+        .map(|(i, annotation)| {
+            let mut line = format!(
+                "{:>4}. roll {} {:<8} {:.4}",
+                i + 1,
+                annotation.ply.roll.get(),
+                annotation.ply.mov.notation(),
+                annotation.chosen,
+            );
+            if annotation.loss > 0.0 {
+                line += &format!(
+                    "  best {} {:.4}  loss {:.4}",
+                    annotation.best_move.notation(),
+                    annotation.best,
+                    annotation.loss
+                );
+            }
+            if let Some(flag) = annotation.flag {
+                line += &format!("  {flag}");
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,233 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::Path,
+};
+
+use serde::Serialize;
+
+use crate::{
+    annotate::{annotate, Annotation, Flag, Thresholds},
+    game::{rules::RuleSet, GameState},
+    record::GameRecord,
+    solve::{load_or_solve, Solution},
+};
+
+/// how far a game has progressed, from the pieces borne off by both sides
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    /// no piece borne off yet
+    Opening,
+    Middle,
+    /// at least half of all pieces borne off
+    Ending,
+}
+
+impl Phase {
+    pub fn of(rules: &RuleSet, game: &GameState) -> Self {
+        let borne_off = game.prot.score + game.opp.score;
+        if borne_off == 0 {
+            Phase::Opening
+        } else if borne_off >= rules.pieces {
+            Phase::Ending
+        } else {
+            Phase::Middle
+        }
+    }
+}
+
+/// totals over a set of decisions, forced moves aren't counted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Accuracy {
+    pub decisions: usize,
+    pub total_loss: f64,
+    /// decisions where the best move was played
+    pub best: usize,
+    pub blunders: usize,
+}
+
+impl Accuracy {
+    fn add(&mut self, annotation: &Annotation) {
+        self.decisions += 1;
+        self.total_loss += annotation.loss;
+        if annotation.loss == 0.0 {
+            self.best += 1;
+        }
+        if annotation.flag == Some(Flag::Blunder) {
+            self.blunders += 1;
+        }
+    }
+
+    pub fn avg_loss(&self) -> f64 {
+        self.total_loss / self.decisions.max(1) as f64
+    }
+
+    pub fn best_rate(&self) -> f64 {
+        self.best as f64 / self.decisions.max(1) as f64
+    }
+
+    pub fn blunder_rate(&self) -> f64 {
+        self.blunders as f64 / self.decisions.max(1) as f64
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PlayerStats {
+    pub games: usize,
+    pub all: Accuracy,
+    pub by_roll: BTreeMap<u8, Accuracy>,
+    pub by_phase: BTreeMap<Phase, Accuracy>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CorpusReport {
+    pub players: BTreeMap<String, PlayerStats>,
+    /// files that couldn't be analysed, with the reason
+    pub skipped: Vec<(String, String)>,
+}
+
+/// The player names of a record, from its `Light` and `Dark` tags, indexed by `Side::index`.
+pub fn player_names(record: &GameRecord) -> [String; 2] {
+    [("Light", "light"), ("Dark", "dark")]
+        .map(|(tag, default)| record.tag(tag).unwrap_or(default).to_string())
+}
+
+/// annotates every game record in the directory, solving each rule set that comes up once
+pub fn analyze_dir(dir: &Path, thresholds: &Thresholds) -> io::Result<CorpusReport> {
+    let mut paths = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    paths.retain(|path| path.is_file());
+    paths.sort();
+
+    let mut solutions: HashMap<RuleSet, Solution> = HashMap::new();
+    let mut report = CorpusReport::default();
+    for path in paths {
+        let name = path.display().to_string();
+        let record = match GameRecord::parse(&fs::read_to_string(&path)?) {
+            Ok(record) => record,
+            Err(err) => {
+                report.skipped.push((name, err.to_string()));
+                continue;
+            }
+        };
+        let solution = solutions
+            .entry(record.rules.clone())
+            .or_insert_with(|| load_or_solve(&record.rules));
+        match annotate(solution, &record, thresholds) {
+            Ok(annotations) => {
+                if let Err(reason) = report.add_game(&record, &annotations) {
+                    report.skipped.push((name, reason));
+                }
+            }
+            Err(err) => report.skipped.push((name, err.to_string())),
+        }
+    }
+    Ok(report)
+}
+
+impl CorpusReport {
+    /// adds a game's decisions to its players, games where one player has both sides aren't
+    /// added since they'd count twice
+    pub fn add_game(
+        &mut self,
+        record: &GameRecord,
+        annotations: &[Annotation],
+    ) -> Result<(), String> {
+        let names = player_names(record);
+        if names[0] == names[1] {
+            return Err(format!("{} plays both sides", names[0]));
+        }
+        for name in &names {
+            self.players.entry(name.clone()).or_default().games += 1;
+        }
+        for annotation in annotations.iter().filter(|annotation| !annotation.forced) {
+            let stats = self
+                .players
+                .get_mut(&names[annotation.ply.game.to_move.index()])
+                .unwrap();
+            stats.all.add(annotation);
+            stats
+                .by_roll
+                .entry(annotation.ply.roll.get())
+                .or_default()
+                .add(annotation);
+            stats
+                .by_phase
+                .entry(Phase::of(&record.rules, &annotation.ply.game.relative))
+                .or_default()
+                .add(annotation);
+        }
+        Ok(())
+    }
+
+    /// one row per player and breakdown
+    pub fn csv(&self) -> String {
+        let mut csv = String::from("player,group,key,decisions,avg_loss,best_rate,blunder_rate\n");
+        let mut row = |player: &str, group: &str, key: String, accuracy: &Accuracy| {
+            csv += &format!(
+                "{},{group},{key},{},{:.6},{:.6},{:.6}\n",
+                csv_field(player),
+                accuracy.decisions,
+                accuracy.avg_loss(),
+                accuracy.best_rate(),
+                accuracy.blunder_rate()
+            );
+        };
+        for (player, stats) in &self.players {
+            row(player, "all", String::new(), &stats.all);
+            for (roll, accuracy) in &stats.by_roll {
+                row(player, "roll", roll.to_string(), accuracy);
+            }
+            for (phase, accuracy) in &stats.by_phase {
+                let phase = format!("{phase:?}").to_lowercase();
+                row(player, "phase", phase, accuracy);
+            }
+        }
+        csv
+    }
+
+    pub fn json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("report is always serializable")
+    }
+}
+
+/// quotes a field with a comma, quote or line break in it, doubling its quotes
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(light: &str, dark: &str) -> GameRecord {
+        let mut record = GameRecord::new(RuleSet::finkel()).unwrap();
+        record.set_tag("Light", light);
+        record.set_tag("Dark", dark);
+        record
+    }
+
+    #[test]
+    fn csv_quotes_names() {
+        let mut report = CorpusReport::default();
+        report
+            .add_game(&record("Smith, J", "say \"hi\""), &[])
+            .unwrap();
+        let csv = report.csv();
+        assert!(csv.contains("\n\"Smith, J\",all,,0,"));
+        assert!(csv.contains("\n\"say \"\"hi\"\"\",all,,0,"));
+    }
+
+    #[test]
+    fn self_play_is_skipped() {
+        let mut report = CorpusReport::default();
+        assert!(report.add_game(&record("alice", "alice"), &[]).is_err());
+        assert!(report.players.is_empty());
+    }
+}
@@ -0,0 +1,112 @@
+use std::fmt;
+
+use crate::game::{rules::RuleSet, GameState, Move, Outcome, PossibleMovesIter, Roll, TeamState};
+
+/// the physical colours, light moves first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Side {
+    Light,
+    Dark,
+}
+
+impl Side {
+    pub fn other(self) -> Self {
+        match self {
+            Side::Light => Side::Dark,
+            Side::Dark => Side::Light,
+        }
+    }
+
+    /// 0 for light and 1 for dark, for indexing per side arrays
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Side::Light => write!(f, "Light"),
+            Side::Dark => write!(f, "Dark"),
+        }
+    }
+}
+
+/// A `GameState` together with the colour of its prot, the side to move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AbsoluteGame {
+    pub to_move: Side,
+    pub relative: GameState,
+}
+
+/// what a move leads to, seen from the board rather than from the mover
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AbsoluteMove {
+    Continue(AbsoluteGame),
+    /// the outcome for `side`
+    End {
+        side: Side,
+        outcome: Outcome,
+    },
+}
+
+impl Default for AbsoluteGame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AbsoluteGame {
+    pub fn new() -> Self {
+        Self::from_relative(Side::Light, GameState::new())
+    }
+
+    pub fn from_relative(to_move: Side, relative: GameState) -> Self {
+        Self { to_move, relative }
+    }
+
+    pub fn team(&self, side: Side) -> &TeamState {
+        if side == self.to_move {
+            &self.relative.prot
+        } else {
+            &self.relative.opp
+        }
+    }
+
+    /// the state with `side` as prot, which is only the side to move if it is `to_move`
+    pub fn relative_to(&self, side: Side) -> GameState {
+        if side == self.to_move {
+            self.relative
+        } else {
+            self.relative.flipped()
+        }
+    }
+
+    /// turns a value for the side to move into one for `side`
+    pub fn val_for(&self, side: Side, val: f64) -> f64 {
+        if side == self.to_move {
+            val
+        } else {
+            1.0 - val
+        }
+    }
+
+    pub fn moves<'a>(&self, rules: &'a RuleSet, roll: Roll) -> PossibleMovesIter<'a> {
+        PossibleMovesIter::new(rules, self.relative, roll)
+    }
+
+    /// applies a move made by the side to move, flipping the state when the turn passes
+    pub fn apply(&self, mov: &Move) -> AbsoluteMove {
+        match *mov {
+            Move::Continue { game, keep_turn } => AbsoluteMove::Continue(if keep_turn {
+                Self::from_relative(self.to_move, game)
+            } else {
+                Self::from_relative(self.to_move.other(), game.flipped())
+            }),
+            Move::End(outcome) => AbsoluteMove::End {
+                side: self.to_move,
+                outcome,
+            },
+        }
+    }
+}
@@ -0,0 +1,422 @@
+use crate::game::{
+    absolute::Side,
+    strip::{StripIndex, StripState},
+    GameStateSmall,
+};
+
+/// Layout of one team's track. Index 0 is the first square a launched piece can reach, and
+/// `track_len` is bearing off. The squares in `shared_start..shared_end` are shared with the
+/// other team, the ones before are that team's private start and the ones after its private end.
+///
+/// Shared squares have the same index for both teams, except for the last `loop_len` of them,
+/// which loop around the far end of the board: over the other team's row, across the end of the
+/// middle row and back along the team's own row. The teams run the loop in opposite directions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Board {
+    track_len: u8,
+    shared_start: u8,
+    shared_end: u8,
+    loop_len: u8,
+    rosettes: StripState,
+}
+
+/// A square of the physical board. Row 0 holds light's private squares, row 1 the shared ones and
+/// row 2 dark's private squares, apart from a loop, which also runs over the side rows. Columns
+/// count from the end where pieces enter the shared row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Coord {
+    pub row: u8,
+    pub col: u8,
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self::finkel()
+    }
+}
+
+impl Board {
+    pub fn finkel() -> Self {
+        Self::new(14, 4, 12, &[3, 7, 13]).unwrap()
+    }
+
+    /// the Masters path over the finkel board's squares. After the private start it runs up the
+    /// middle row to its second last square and loops round the far end, over the other team's
+    /// two end squares, the last middle square and the team's own two end squares, before
+    /// bearing off. Every square after the private start is shared
+    pub fn masters() -> Self {
+        Self::new(16, 4, 16, &[3, 7, 11, 15])
+            .and_then(|board| board.with_loop(5))
+            .unwrap()
+    }
+
+    // the private stretches are packed from `StripState::start_bits` and `end_bits`, one byte each
+    const MAX_PRIVATE_LEN: u8 = u8::BITS as u8;
+
+    pub fn new(track_len: u8, shared_start: u8, shared_end: u8, rosettes: &[u8]) -> Option<Self> {
+        if track_len > StripIndex::MAX_TRACK_LEN
+            || shared_start > shared_end
+            || shared_end > track_len
+            || shared_start > Self::MAX_PRIVATE_LEN
+            || track_len - shared_end > Self::MAX_PRIVATE_LEN
+            || rosettes.iter().any(|&i| i >= track_len)
+        {
+            return None;
+        }
+        let mut rosette_strip = StripState::new();
+        for &i in rosettes {
+            rosette_strip.set(StripIndex(i), true);
+        }
+        let board = Self {
+            track_len,
+            shared_start,
+            shared_end,
+            loop_len: 0,
+            rosettes: rosette_strip,
+        };
+        if board.packed_bits() > GameStateSmall::TURN_SHIFT {
+            None
+        } else {
+            Some(board)
+        }
+    }
+
+    /// Makes the last `loop_len` shared squares a loop. The loop has an odd length of at least 3,
+    /// so its middle square is on the middle row, takes the place of the private end, and needs
+    /// its side row squares clear of the private start. A rosette in the loop needs a rosette on
+    /// the square the other team reaches it by, which is the same physical square.
+    pub fn with_loop(self, loop_len: u8) -> Option<Self> {
+        let board = Self { loop_len, ..self };
+        let aligned_len = self.shared_len().checked_sub(loop_len)?;
+        if loop_len == 0 {
+            return Some(board);
+        }
+        if loop_len < 3
+            || loop_len.is_multiple_of(2)
+            || self.end_len() != 0
+            || aligned_len + 1 < self.start_len() + loop_len / 2
+            || board
+                .loop_indices()
+                .any(|i| board.is_rosette(i) != board.is_rosette(board.loop_mirror(i)))
+        {
+            return None;
+        }
+        Some(board)
+    }
+
+    pub fn track_len(&self) -> u8 {
+        self.track_len
+    }
+
+    pub fn start_len(&self) -> u8 {
+        self.shared_start
+    }
+
+    pub fn shared_len(&self) -> u8 {
+        self.shared_end - self.shared_start
+    }
+
+    pub fn end_len(&self) -> u8 {
+        self.track_len - self.shared_end
+    }
+
+    pub fn index(&self, i: u8) -> Option<StripIndex> {
+        if i < self.track_len {
+            Some(StripIndex(i))
+        } else {
+            None
+        }
+    }
+
+    pub fn indices(&self) -> impl DoubleEndedIterator<Item = StripIndex> {
+        (0..self.track_len).map(StripIndex)
+    }
+
+    pub fn start_indices(&self) -> impl DoubleEndedIterator<Item = StripIndex> {
+        (0..self.shared_start).map(StripIndex)
+    }
+
+    pub fn shared_indices(&self) -> impl DoubleEndedIterator<Item = StripIndex> {
+        (self.shared_start..self.shared_end).map(StripIndex)
+    }
+
+    pub fn end_indices(&self) -> impl DoubleEndedIterator<Item = StripIndex> {
+        (self.shared_end..self.track_len).map(StripIndex)
+    }
+
+    pub fn is_shared(&self, i: StripIndex) -> bool {
+        (self.shared_start..self.shared_end).contains(&i.0)
+    }
+
+    pub fn loop_len(&self) -> u8 {
+        self.loop_len
+    }
+
+    fn loop_start(&self) -> u8 {
+        self.shared_end - self.loop_len
+    }
+
+    fn loop_indices(&self) -> impl DoubleEndedIterator<Item = StripIndex> {
+        (self.loop_start()..self.shared_end).map(StripIndex)
+    }
+
+    fn loop_mirror(&self, i: StripIndex) -> StripIndex {
+        StripIndex(self.loop_start() + self.shared_end - 1 - i.0)
+    }
+
+    /// the other team's index for the same physical square as `i`, None for private squares
+    pub fn opponent_index(&self, i: StripIndex) -> Option<StripIndex> {
+        if !self.is_shared(i) {
+            None
+        } else if i.0 >= self.loop_start() {
+            Some(self.loop_mirror(i))
+        } else {
+            Some(i)
+        }
+    }
+
+    pub fn is_rosette(&self, i: StripIndex) -> bool {
+        self.rosettes.get(i)
+    }
+
+    /// number of bits needed for the base 3 encoding of the shared squares
+    pub fn shared_bits(&self) -> u32 {
+        let states = 3u64.pow(self.shared_len() as u32);
+        u64::BITS - (states - 1).leading_zeros()
+    }
+
+    /// number of bits used by GameStateSmall on this board
+    pub fn packed_bits(&self) -> u32 {
+        2 * 3 + 2 * (self.start_len() + self.end_len()) as u32 + self.shared_bits()
+    }
+
+    /// describes the board for file names, e.g. `14s4-12r3.7.13`
+    pub fn key(&self) -> String {
+        let rosettes = self
+            .indices()
+            .filter(|&i| self.is_rosette(i))
+            .map(|i| i.0.to_string())
+            .collect::<Vec<_>>()
+            .join(".");
+        let loop_key = if self.loop_len > 0 {
+            format!("l{}", self.loop_len)
+        } else {
+            String::new()
+        };
+        format!(
+            "{}s{}-{}{loop_key}r{rosettes}",
+            self.track_len, self.shared_start, self.shared_end
+        )
+    }
+
+    pub const ROWS: u8 = 3;
+
+    /// width of the board, the private squares run back along the side rows and leave a gap
+    /// wherever the shared row is longer than both private stretches together. A loop adds a
+    /// column for its middle square
+    pub fn cols(&self) -> u8 {
+        if self.loop_len > 0 {
+            self.shared_len() - self.loop_len + 1
+        } else {
+            self.shared_len().max(self.start_len() + self.end_len())
+        }
+    }
+
+    /// where `side`'s square `i` is
+    pub fn coord(&self, side: Side, i: StripIndex) -> Coord {
+        let own_row = match side {
+            Side::Light => 0,
+            Side::Dark => 2,
+        };
+        if i.0 < self.shared_start {
+            Coord {
+                row: own_row,
+                col: self.shared_start - 1 - i.0,
+            }
+        } else if (self.loop_start()..self.shared_end).contains(&i.0) {
+            // half the loop on each side row, ending in the last column
+            let half = self.loop_len / 2;
+            let last_col = self.cols() - 1;
+            let step = i.0 - self.loop_start();
+            match step.cmp(&half) {
+                std::cmp::Ordering::Less => Coord {
+                    row: 2 - own_row,
+                    col: last_col + 1 + step - half,
+                },
+                std::cmp::Ordering::Equal => Coord {
+                    row: 1,
+                    col: last_col,
+                },
+                std::cmp::Ordering::Greater => Coord {
+                    row: own_row,
+                    col: last_col + half + 1 - step,
+                },
+            }
+        } else if i.0 < self.shared_end {
+            Coord {
+                row: 1,
+                col: i.0 - self.shared_start,
+            }
+        } else {
+            Coord {
+                row: own_row,
+                col: self.cols() - 1 - (i.0 - self.shared_end),
+            }
+        }
+    }
+
+    /// the square of `side`'s track at `coord`, None for gaps, the other side's private squares
+    /// and coordinates off the board
+    pub fn index_at(&self, side: Side, coord: Coord) -> Option<StripIndex> {
+        self.indices().find(|&i| self.coord(side, i) == coord)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::game::{
+        rules::RuleSet,
+        strip::{Delta, MoveSource},
+        Destination, GameState, Move, MoveRecord, PieceMove,
+    };
+
+    fn coord(row: u8, col: u8) -> Coord {
+        Coord { row, col }
+    }
+
+    /// the rows `render` drew the finkel board from before boards had coordinates
+    const SIDE_ROW: [Option<u8>; 8] = [
+        Some(3),
+        Some(2),
+        Some(1),
+        Some(0),
+        None,
+        None,
+        Some(13),
+        Some(12),
+    ];
+    const MID_ROW: [u8; 8] = [4, 5, 6, 7, 8, 9, 10, 11];
+
+    #[test]
+    fn finkel_matches_the_old_render_rows() {
+        let board = Board::finkel();
+        assert_eq!(board.cols(), 8);
+        for col in 0..8 {
+            for (side, row) in [(Side::Light, 0), (Side::Dark, 2)] {
+                assert_eq!(
+                    board.index_at(side, coord(row, col)),
+                    SIDE_ROW[col as usize].map(StripIndex)
+                );
+                assert_eq!(board.index_at(side, coord(2 - row, col)), None);
+                assert_eq!(
+                    board.index_at(side, coord(1, col)),
+                    Some(StripIndex(MID_ROW[col as usize]))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn coords_round_trip() {
+        // the last board's private stretches are longer than its shared row
+        for board in [
+            Board::finkel(),
+            Board::masters(),
+            Board::new(14, 6, 10, &[5, 8, 13]).unwrap(),
+        ] {
+            for side in [Side::Light, Side::Dark] {
+                for i in board.indices() {
+                    let square = board.coord(side, i);
+                    assert!(square.row < Board::ROWS && square.col < board.cols());
+                    assert_eq!(board.index_at(side, square), Some(i), "{board:?} {side}");
+                }
+                let squares = board
+                    .indices()
+                    .map(|i| board.coord(side, i))
+                    .collect::<HashSet<_>>();
+                assert_eq!(squares.len(), board.track_len() as usize);
+            }
+            assert_eq!(board.index_at(Side::Light, coord(3, 0)), None);
+            assert_eq!(board.index_at(Side::Light, coord(1, board.cols())), None);
+        }
+    }
+
+    #[test]
+    fn masters_path() {
+        let board = Board::masters();
+        assert_eq!(board.cols(), 8);
+        let path: Vec<_> = board
+            .indices()
+            .map(|i| board.coord(Side::Light, i))
+            .collect();
+        let mut expected: Vec<_> = (0..4).rev().map(|col| coord(0, col)).collect();
+        expected.extend((0..7).map(|col| coord(1, col)));
+        expected.extend([
+            coord(2, 6),
+            coord(2, 7),
+            coord(1, 7),
+            coord(0, 7),
+            coord(0, 6),
+        ]);
+        assert_eq!(path, expected);
+        // the same rosettes as the finkel board
+        let rosettes: Vec<_> = board
+            .indices()
+            .filter(|&i| board.is_rosette(i))
+            .map(|i| board.coord(Side::Light, i))
+            .collect();
+        assert_eq!(
+            rosettes,
+            [coord(0, 0), coord(1, 3), coord(2, 6), coord(0, 6)]
+        );
+    }
+
+    #[test]
+    fn opponent_index_is_the_same_square() {
+        for board in [Board::finkel(), Board::masters()] {
+            for i in board.indices() {
+                let square = board.coord(Side::Light, i);
+                assert_eq!(
+                    board.opponent_index(i),
+                    board.index_at(Side::Dark, square),
+                    "{square:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn captures_on_the_loop() {
+        let rules = RuleSet::masters();
+        // prot moves from the middle row onto the other team's row, where the other team has
+        // its second last square
+        let mut game = GameState::new();
+        game.prot.strip.set(StripIndex(9), true);
+        game.opp.strip.set(StripIndex(14), true);
+        let mov = game.move_piece(
+            &rules,
+            MoveSource::Index(StripIndex(9)),
+            Delta::new(3).unwrap(),
+        );
+        let mut expected = GameState::new();
+        expected.prot.strip.set(StripIndex(12), true);
+        assert_eq!(
+            mov,
+            Some(MoveRecord {
+                piece: Some(PieceMove {
+                    source: MoveSource::Index(StripIndex(9)),
+                    dest: Destination::Index(StripIndex(12)),
+                    capture: true,
+                    rosette: false,
+                }),
+                result: Move::Continue {
+                    game: expected,
+                    keep_turn: false,
+                },
+            })
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use num_traits::Float;
+use rand::Rng;
+
+use crate::game::Roll;
+
+/// The possible rolls and how often they come up. Weights are relative, so a table of
+/// `[(0, 1), (1, 4), (2, 6), (3, 4), (4, 1)]` is four binary dice.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DiceModel {
+    // sorted by roll, no duplicate rolls and no zero weights
+    outcomes: Vec<(u8, u32)>,
+}
+
+impl Default for DiceModel {
+    fn default() -> Self {
+        Self::binary(4).unwrap()
+    }
+}
+
+impl DiceModel {
+    /// n two sided dice, summed. None if n is a longer move than a roll can be
+    pub fn binary(n: u8) -> Option<Self> {
+        Roll::new(n)?;
+        let outcomes = (0..=n).map(|k| (k, binomial(n, k))).collect();
+        Self::table(outcomes)
+    }
+
+    /// n tetrahedral dice with two of their four tips marked, which roll like binary dice
+    pub fn tetrahedral(n: u8) -> Option<Self> {
+        Self::binary(n)
+    }
+
+    /// n binary dice where rolling no marks moves n + 1 instead of passing. None if n + 1 is a
+    /// longer move than a roll can be
+    pub fn binary_zero_max(n: u8) -> Option<Self> {
+        Roll::new(n.checked_add(1)?)?;
+        let outcomes = (0..=n)
+            .map(|k| (if k == 0 { n + 1 } else { k }, binomial(n, k)))
+            .collect();
+        Self::table(outcomes)
+    }
+
+    /// a user supplied table of (roll, weight), repeated rolls have their weights added. None if
+    /// the weights add up to more than a u32
+    pub fn table(mut outcomes: Vec<(u8, u32)>) -> Option<Self> {
+        outcomes
+            .iter()
+            .try_fold(0u32, |total, (_, weight)| total.checked_add(*weight))?;
+        outcomes.retain(|(_, weight)| *weight > 0);
+        outcomes.sort();
+        outcomes.dedup_by(|(roll, weight), (kept_roll, kept_weight)| {
+            let same = roll == kept_roll;
+            if same {
+                *kept_weight += *weight;
+            }
+            same
+        });
+        if outcomes.is_empty() || outcomes.iter().any(|(roll, _)| Roll::new(*roll).is_none()) {
+            None
+        } else {
+            Some(Self { outcomes })
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.outcomes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.outcomes.is_empty()
+    }
+
+    pub fn rolls(&self) -> impl Iterator<Item = Roll> + Clone + '_ {
+        self.outcomes
+            .iter()
+            .map(|(roll, _)| Roll::new(*roll).unwrap())
+    }
+
+    /// the roll with this number of moves, if it can come up
+    pub fn roll(&self, roll: u8) -> Option<Roll> {
+        self.outcomes
+            .iter()
+            .find(|(outcome, _)| *outcome == roll)
+            .and_then(|(roll, _)| Roll::new(*roll))
+    }
+
+    /// probability of each roll, in the same order as `rolls`
+    pub fn weights<T: Float>(&self) -> Vec<T> {
+        let total: u32 = self.outcomes.iter().map(|(_, weight)| weight).sum();
+        self.outcomes
+            .iter()
+            .map(|(_, weight)| T::from(*weight as f64 / total as f64).unwrap())
+            .collect()
+    }
+
+    /// parses `binary:N`, `tetrahedral:N`, `zero-max:N` or a table of rolls and weights like
+    /// `0:1,1:4,2:6,3:4,4:1`
+    pub fn parse(spec: &str) -> Option<Self> {
+        match spec.split_once(':')? {
+            ("binary", n) => Self::binary(n.parse().ok()?),
+            ("tetrahedral", n) => Self::tetrahedral(n.parse().ok()?),
+            ("zero-max", n) => Self::binary_zero_max(n.parse().ok()?),
+            _ => Self::table(
+                spec.split(',')
+                    .map(|outcome| {
+                        let (roll, weight) = outcome.split_once(':')?;
+                        Some((roll.parse().ok()?, weight.parse().ok()?))
+                    })
+                    .collect::<Option<_>>()?,
+            ),
+        }
+    }
+
+    /// the table form accepted by `parse`
+    pub fn spec(&self) -> String {
+        self.outcomes
+            .iter()
+            .map(|(roll, weight)| format!("{roll}:{weight}"))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// rolls the dice
+    pub fn sample(&self, rng: &mut impl Rng) -> Roll {
+        let total: u32 = self.outcomes.iter().map(|(_, weight)| weight).sum();
+        let mut pick = rng.random_range(0..total);
+        for (roll, weight) in &self.outcomes {
+            if pick < *weight {
+                return Roll::new(*roll).unwrap();
+            }
+            pick -= weight;
+        }
+        unreachable!("pick is below the total weight")
+    }
+
+    /// describes the dice for file names, e.g. `0w1.1w4.2w6.3w4.4w1`
+    pub fn key(&self) -> String {
+        self.outcomes
+            .iter()
+            .map(|(roll, weight)| format!("{roll}w{weight}"))
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+}
+
+fn binomial(n: u8, k: u8) -> u32 {
+    (0..k as u32).fold(1, |acc, i| acc * (n as u32 - i) / (i + 1))
+}
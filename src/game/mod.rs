@@ -1,19 +1,27 @@
-use num_traits::Float;
-
 use crate::{
-    game::strip::{Delta, DeltaResult, MoveSource, Square, StripIndex, StripState},
-    solve::perma::PermaKey,
+    game::{
+        board::Board,
+        rules::RuleSet,
+        strip::{Delta, DeltaResult, MoveSource, Square, StripIndex, StripState},
+        validate::PositionError,
+    },
     successor::{Succ, SuccIter},
 };
 
+pub mod absolute;
+pub mod board;
+pub mod dice;
+pub mod notation;
+pub mod rules;
 pub mod strip;
-
-pub const GOAL_SCORE: u8 = 7;
+pub mod validate;
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Copy)]
 pub struct GameState {
     pub prot: TeamState,
     pub opp: TeamState,
+    /// turns passed so far, only counted when the rules have a turn limit
+    pub turn: u8,
 }
 
 impl Default for GameState {
@@ -27,87 +35,83 @@ impl GameState {
         Self {
             prot: TeamState::new(),
             opp: TeamState::new(),
+            turn: 0,
         }
     }
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Copy)]
-// [3:score1][3:score2][4:start1][2:end1][4:start2][2:end2][13:shared]
-// 30..=28   27..=25   24..=21   20..=19 18..=15   14..=13    12..=0
-pub struct GameStateSmall(u32);
-
-impl PartialOrd for GameStateSmall {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
-impl Ord for GameStateSmall {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        PermaKey::new(GameState::from(*self))
-            .cmp(&PermaKey::new(GameState::from(*other)))
-            .then_with(|| self.0.cmp(&other.0))
-    }
-}
-impl From<GameState> for GameStateSmall {
-    fn from(value: GameState) -> Self {
-        let mut res = 0;
-        res |= ((value.prot.score & 0b0000_0111) as u32) << 28;
-        res |= ((value.opp.score & 0b0000_0111) as u32) << 25;
-
-        res |= (value.prot.strip.start_bits() as u32) << 21;
-        res |= (value.prot.strip.end_bits() as u32) << 19;
-
-        res |= (value.opp.strip.start_bits() as u32) << 15;
-        res |= (value.opp.strip.end_bits() as u32) << 13;
-
-        let shared = StripIndex::succ_iter()
-            .skip(4)
-            .take(8)
-            .fold(0u32, |acc, i| {
-                acc * 3
-                    + match (value.prot.strip.get(i), value.opp.strip.get(i)) {
-                        (false, false) => 0,
-                        (true, false) => 1,
-                        (false, true) => 2,
-                        _ => panic!("prot and opp overlap"),
-                    }
-            });
-        res |= 0b0111_1111_1111_1111 & shared;
-        Self(res)
+// [3:score1][3:score2][S:start1][E:end1][S:start2][E:end2][shared in base 3]
+// where S and E are the private start and end lengths of the board, so on the finkel board
+// 30..=28   27..=25   24..=21   20..=19 18..=15   14..=13  12..=0
+// longer boards use the bits above 31
+pub struct GameStateSmall(u64);
+
+impl GameStateSmall {
+    // the turn sits in the top byte, above any board's packing
+    pub const TURN_SHIFT: u32 = 56;
+
+    /// Packs a state the solver produced, which is always valid. Everything else goes through
+    /// `GameStateSmall::try_from`, which validates the state first.
+    ///
+    /// # Panics
+    ///
+    /// if both teams have a piece on the same shared square
+    pub(crate) fn pack(game: GameState, board: &Board) -> Self {
+        let shared = board.shared_indices().fold(0u64, |acc, i| {
+            let opp_i = board.opponent_index(i).unwrap();
+            acc * 3
+                + match (game.prot.strip.get(i), game.opp.strip.get(opp_i)) {
+                    (false, false) => 0,
+                    (true, false) => 1,
+                    (false, true) => 2,
+                    _ => panic!("prot and opp overlap"),
+                }
+        });
+        let fields = [
+            (game.prot.score as u64, 3),
+            (game.opp.score as u64, 3),
+            (game.prot.strip.start_bits(board) as u64, board.start_len()),
+            (game.prot.strip.end_bits(board) as u64, board.end_len()),
+            (game.opp.strip.start_bits(board) as u64, board.start_len()),
+            (game.opp.strip.end_bits(board) as u64, board.end_len()),
+        ];
+        let res = fields
+            .into_iter()
+            .fold(0u64, |acc, (bits, len)| (acc << len) | (bits & mask(len)));
+        Self(((game.turn as u64) << Self::TURN_SHIFT) | (res << board.shared_bits()) | shared)
     }
-}
-
-impl From<GameStateSmall> for GameState {
-    fn from(small: GameStateSmall) -> Self {
-        let bits = small.0;
-
-        let score1 = ((bits >> 28) & 0b111) as u8;
-        let score2 = ((bits >> 25) & 0b111) as u8;
-
-        let start1 = ((bits >> 21) & 0b1111) as u8;
-        let end1 = ((bits >> 19) & 0b11) as u8;
-
-        let start2 = ((bits >> 15) & 0b1111) as u8;
-        let end2 = ((bits >> 13) & 0b11) as u8;
 
-        let shared_encoded = bits & 0b01_1111_1111_1111;
-
-        let mut prot_strip = StripState::from_start_and_end(start1, end1);
-        let mut opp_strip = StripState::from_start_and_end(start2, end2);
-
-        let mut shared = shared_encoded;
-        for i in (4..12).rev() {
+    pub fn unpack(self, board: &Board) -> GameState {
+        let mut bits = self.0;
+        let mut take = |len: u32| {
+            let field = bits & mask(len as u8);
+            bits = bits.checked_shr(len).unwrap_or(0);
+            field
+        };
+
+        let mut shared = take(board.shared_bits());
+        let end2 = take(board.end_len() as u32) as u8;
+        let start2 = take(board.start_len() as u32) as u8;
+        let end1 = take(board.end_len() as u32) as u8;
+        let start1 = take(board.start_len() as u32) as u8;
+        let score2 = take(3) as u8;
+        let score1 = take(3) as u8;
+
+        let mut prot_strip = StripState::from_start_and_end(board, start1, end1);
+        let mut opp_strip = StripState::from_start_and_end(board, start2, end2);
+
+        for i in board.shared_indices().rev() {
             let digit = shared % 3;
             shared /= 3;
 
             match digit {
                 0 => {}
                 1 => {
-                    prot_strip.set(StripIndex::new(i).unwrap(), true);
+                    prot_strip.set(i, true);
                 }
                 2 => {
-                    opp_strip.set(StripIndex::new(i).unwrap(), true);
+                    opp_strip.set(board.opponent_index(i).unwrap(), true);
                 }
                 _ => unreachable!(),
             }
@@ -122,17 +126,22 @@ impl From<GameStateSmall> for GameState {
                 score: score2,
                 strip: opp_strip,
             },
+            turn: (self.0 >> Self::TURN_SHIFT) as u8,
         }
     }
 }
 
-impl From<u32> for GameStateSmall {
-    fn from(value: u32) -> Self {
+fn mask(len: u8) -> u64 {
+    1u64.checked_shl(len as u32).unwrap_or(0).wrapping_sub(1)
+}
+
+impl From<u64> for GameStateSmall {
+    fn from(value: u64) -> Self {
         Self(value)
     }
 }
 
-impl From<GameStateSmall> for u32 {
+impl From<GameStateSmall> for u64 {
     fn from(value: GameStateSmall) -> Self {
         value.0
     }
@@ -160,11 +169,14 @@ impl TeamState {
 }
 
 impl TeamState {
-    fn remaining(&self) -> u8 {
-        GOAL_SCORE - self.strip.count_pieces() - self.score
+    /// pieces still waiting to be launched
+    pub fn remaining(&self, rules: &RuleSet) -> u8 {
+        rules
+            .pieces
+            .saturating_sub(self.strip.count_pieces().saturating_add(self.score))
     }
 
-    fn remove_move_source(&self, source: MoveSource) -> Option<Self> {
+    fn remove_move_source(&self, rules: &RuleSet, source: MoveSource) -> Option<Self> {
         match source {
             MoveSource::Index(i) => {
                 if self.strip.get(i) {
@@ -176,7 +188,7 @@ impl TeamState {
                 }
             }
             MoveSource::Launch => {
-                if self.remaining() == 0 {
+                if self.remaining(rules) == 0 {
                     None
                 } else {
                     Some(*self)
@@ -191,52 +203,104 @@ impl GameState {
         GameState {
             prot: self.opp,
             opp: self.prot,
+            turn: self.turn,
         }
     }
 
-    pub fn player_at_i(&self, i: StripIndex) -> Option<Player> {
-        if i.both_teams_accessible() {
-            match (self.prot.strip.get(i), self.opp.strip.get(i)) {
-                (true, false) => Some(Player::Prot),
-                (false, true) => Some(Player::Opp),
-                (false, false) => None,
-                _ => panic!("prot and opp strips both have piece at {i:?}"),
+    /// the move that leaves prot in this state, counting the turn if it passes
+    fn finish_move(mut self, rules: &RuleSet, keep_turn: bool) -> Move {
+        if let (Some(limit), false) = (rules.turn_limit, keep_turn) {
+            self.turn += 1;
+            if self.turn >= limit.turns {
+                return Move::End(limit.outcome(&self));
             }
-        } else if self.prot.strip.get(i) {
-            Some(Player::Prot)
-        } else {
-            None
+        }
+        Move::Continue {
+            game: self,
+            keep_turn,
         }
     }
 
-    pub fn move_piece(&self, source: MoveSource, delta: Delta) -> Option<Move> {
+    /// the team with a piece on prot's square `i`, an error if both teams have one there
+    pub fn player_at_i(
+        &self,
+        board: &Board,
+        i: StripIndex,
+    ) -> Result<Option<Player>, PositionError> {
+        let opp = board
+            .opponent_index(i)
+            .is_some_and(|opp_i| self.opp.strip.get(opp_i));
+        match (self.prot.strip.get(i), opp) {
+            (true, true) => Err(PositionError::Overlap(i)),
+            (true, false) => Ok(Some(Player::Prot)),
+            (false, true) => Ok(Some(Player::Opp)),
+            (false, false) => Ok(None),
+        }
+    }
+
+    /// the record of passing the turn without moving a piece
+    pub fn pass(&self, rules: &RuleSet) -> MoveRecord {
+        MoveRecord {
+            piece: None,
+            result: self.finish_move(rules, false),
+        }
+    }
+
+    pub fn move_piece(
+        &self,
+        rules: &RuleSet,
+        source: MoveSource,
+        delta: Delta,
+    ) -> Option<MoveRecord> {
         let mut game = *self;
-        game.prot = game.prot.remove_move_source(source)?;
-        match source.apply_delta(delta) {
+        game.prot = game.prot.remove_move_source(rules, source)?;
+        match source.apply_delta(delta, rules) {
             DeltaResult::OutOfBounds => None,
             DeltaResult::Score => {
                 game.prot.score += 1;
-                Some(if game.prot.score == GOAL_SCORE {
-                    Move::End
-                } else {
-                    Move::Continue {
-                        game,
-                        keep_turn: false,
-                    }
+                Some(MoveRecord {
+                    piece: Some(PieceMove {
+                        source,
+                        dest: Destination::BearOff,
+                        capture: false,
+                        rosette: false,
+                    }),
+                    result: if game.prot.score == rules.pieces {
+                        Move::End(Outcome::Win)
+                    } else {
+                        game.finish_move(rules, false)
+                    },
                 })
             }
-            DeltaResult::Index(new_i) => match (self.player_at_i(new_i), new_i.square()) {
+            DeltaResult::Index(new_i) => match (
+                // there's no move onto a square both teams are on, which `validate` rejects
+                self.player_at_i(&rules.board, new_i).ok()?,
+                new_i.square(&rules.board),
+            ) {
                 (Some(Player::Prot), _) => None,
-                (Some(Player::Opp), Square::Flower) => None,
+                (Some(Player::Opp), Square::Flower) if rules.safe_rosettes => None,
                 (opp, square) => {
                     let caused_deletion = matches!(opp, Some(Player::Opp));
                     if caused_deletion {
-                        game.opp.strip.set(new_i, false);
+                        // the captured piece is on the same square, which only a loop indexes
+                        // differently for the other team
+                        let opp_i = rules.board.opponent_index(new_i).unwrap();
+                        game.opp.strip.set(opp_i, false);
                     }
                     game.prot.strip.set(new_i, true);
-                    Some(Move::Continue {
-                        game,
-                        keep_turn: matches!(square, Square::Flower),
+                    let on_flower = matches!(square, Square::Flower);
+                    let keep_turn = on_flower
+                        && rules
+                            .extra_turn
+                            .on_rosette(new_i.both_teams_accessible(&rules.board));
+                    Some(MoveRecord {
+                        piece: Some(PieceMove {
+                            source,
+                            dest: Destination::Index(new_i),
+                            capture: caused_deletion,
+                            rosette: on_flower,
+                        }),
+                        result: game.finish_move(rules, keep_turn),
                     })
                 }
             },
@@ -244,7 +308,7 @@ impl GameState {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Player {
     Prot,
     Opp,
@@ -252,56 +316,96 @@ pub enum Player {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Move {
-    Continue { game: GameState, keep_turn: bool },
-    End,
+    Continue {
+        game: GameState,
+        keep_turn: bool,
+    },
+    /// the game is over, seen from the player who moved
+    End(Outcome),
 }
 
-#[derive(Debug, Clone, Copy)]
-pub enum Roll {
-    Zero,
-    Delta(Delta),
+/// a legal move as produced by `PossibleMovesIter`, describing what moved along with the
+/// resulting state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoveRecord {
+    /// the piece that moved, None when no piece can move and the turn is passed
+    pub piece: Option<PieceMove>,
+    pub result: Move,
 }
-impl Roll {
-    pub fn vals() -> [Roll; 5] {
-        [
-            Roll::Zero,
-            Roll::Delta(Delta::new(1).unwrap()),
-            Roll::Delta(Delta::new(2).unwrap()),
-            Roll::Delta(Delta::new(3).unwrap()),
-            Roll::Delta(Delta::new(4).unwrap()),
-        ]
+
+impl MoveRecord {
+    pub fn is_pass(&self) -> bool {
+        self.piece.is_none()
     }
 
-    pub fn from_index(index: usize) -> Option<Self> {
-        Self::vals().get(index).cloned()
+    pub fn is_capture(&self) -> bool {
+        self.piece.is_some_and(|piece| piece.capture)
     }
 
-    pub fn weight<T: Float>(&self) -> T {
-        T::from(match self {
-            Roll::Zero => 1.0 / 16.0,
-            Roll::Delta(delta) if delta.get() == 1 => 4.0 / 16.0,
-            Roll::Delta(delta) if delta.get() == 2 => 6.0 / 16.0,
-            Roll::Delta(delta) if delta.get() == 3 => 4.0 / 16.0,
-            Roll::Delta(delta) if delta.get() == 4 => 1.0 / 16.0,
-            _ => unreachable!(),
-        })
-        .unwrap()
+    pub fn is_rosette(&self) -> bool {
+        self.piece.is_some_and(|piece| piece.rosette)
     }
 }
 
-impl Succ for Roll {
-    fn first() -> Self {
-        Self::Zero
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PieceMove {
+    pub source: MoveSource,
+    pub dest: Destination,
+    /// whether an opponent piece was sent back
+    pub capture: bool,
+    /// whether the piece landed on a rosette, which may or may not give another turn
+    pub rosette: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Destination {
+    Index(StripIndex),
+    BearOff,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    Win,
+    Draw,
+    Loss,
+}
+
+impl Outcome {
+    /// the value of the outcome when draws are worth half a win
+    pub fn val(&self) -> f64 {
+        match self {
+            Outcome::Win => 1.0,
+            Outcome::Draw => 0.5,
+            Outcome::Loss => 0.0,
+        }
     }
-    fn succ(&self) -> Option<Self> {
-        Some(match self {
-            Self::Zero => Self::Delta(Delta::first()),
-            Self::Delta(d) => Self::Delta(d.succ()?),
-        })
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Roll {
+    Zero,
+    Delta(Delta),
+}
+impl Roll {
+    pub fn new(roll: u8) -> Option<Self> {
+        if roll == 0 {
+            Some(Roll::Zero)
+        } else {
+            Delta::new(roll).map(Roll::Delta)
+        }
+    }
+
+    pub fn get(&self) -> u8 {
+        match self {
+            Roll::Zero => 0,
+            Roll::Delta(delta) => delta.get(),
+        }
     }
 }
+
 #[derive(Debug, Clone)]
-pub struct PossibleMovesIter {
+pub struct PossibleMovesIter<'a> {
+    rules: &'a RuleSet,
     game: GameState,
     roll: PossibleMovesRoll,
 }
@@ -315,9 +419,10 @@ pub enum PossibleMovesRoll {
     },
 }
 
-impl PossibleMovesIter {
-    pub fn new(game: GameState, roll: Roll) -> Self {
+impl<'a> PossibleMovesIter<'a> {
+    pub fn new(rules: &'a RuleSet, game: GameState, roll: Roll) -> Self {
         Self {
+            rules,
             game,
             roll: match roll {
                 Roll::Zero => PossibleMovesRoll::Zero(false),
@@ -331,8 +436,8 @@ impl PossibleMovesIter {
     }
 }
 
-impl Iterator for PossibleMovesIter {
-    type Item = Move;
+impl Iterator for PossibleMovesIter<'_> {
+    type Item = MoveRecord;
 
     fn next(&mut self) -> Option<Self::Item> {
         match &mut self.roll {
@@ -341,10 +446,7 @@ impl Iterator for PossibleMovesIter {
                     None
                 } else {
                     *done = true;
-                    Some(Move::Continue {
-                        game: self.game,
-                        keep_turn: false,
-                    })
+                    Some(self.game.pass(self.rules))
                 }
             }
             PossibleMovesRoll::Delta {
@@ -352,7 +454,8 @@ impl Iterator for PossibleMovesIter {
                 delta,
                 provided_one_move,
             } => {
-                let mov = source_iter.find_map(|source| self.game.move_piece(source, *delta));
+                let mov =
+                    source_iter.find_map(|source| self.game.move_piece(self.rules, source, *delta));
                 match mov {
                     Some(mov) => {
                         *provided_one_move = true;
@@ -363,10 +466,7 @@ impl Iterator for PossibleMovesIter {
                             None
                         } else {
                             *provided_one_move = true;
-                            Some(Move::Continue {
-                                game: self.game,
-                                keep_turn: false,
-                            })
+                            Some(self.game.pass(self.rules))
                         }
                     }
                 }
@@ -0,0 +1,448 @@
+use std::fmt;
+
+use itertools::Itertools;
+
+use crate::game::{
+    rules::RuleSet,
+    strip::{MoveSource, StripIndex, StripState},
+    validate::PositionError,
+    Destination, GameState, MoveRecord, PossibleMovesIter, Roll, TeamState,
+};
+
+impl GameState {
+    /// Text notation for positions, e.g. `P3P9/14 5:0/7:0` on the finkel board.
+    ///
+    /// The fields are the track of the team to move, the other team's track, each team's
+    /// waiting pieces and score, and the turn when the rules have a turn limit. A track lists its
+    /// squares from index 0 to the end, with `P` or `O` for a piece and a number for a run of
+    /// empty squares. The team to move is always written first, with `P`, like in `render`.
+    pub fn notation(&self, rules: &RuleSet) -> String {
+        let team = |team: &TeamState| format!("{}:{}", team.remaining(rules), team.score);
+        let mut notation = format!(
+            "{}/{} {}/{}",
+            track_notation(rules, self.prot.strip, 'P'),
+            track_notation(rules, self.opp.strip, 'O'),
+            team(&self.prot),
+            team(&self.opp),
+        );
+        if rules.turn_limit.is_some() {
+            notation += &format!(" {}", self.turn);
+        }
+        notation
+    }
+
+    /// Parses what `notation` writes. Each position has exactly one notation, so anything else,
+    /// such as a run of `0` empty squares, is rejected.
+    pub fn from_notation(notation: &str, rules: &RuleSet) -> Result<Self, NotationError> {
+        let fields: Vec<_> = notation.split_whitespace().collect();
+        let (tracks, counts, turn) = match (fields.as_slice(), rules.turn_limit) {
+            (&[tracks, counts], None) => (tracks, counts, None),
+            (&[tracks, counts, turn], Some(_)) => (tracks, counts, Some(turn)),
+            _ => return Err(NotationError::FieldCount),
+        };
+        let (prot_track, opp_track) = tracks
+            .split_once('/')
+            .ok_or(NotationError::Syntax("tracks"))?;
+        let (prot_counts, opp_counts) = counts
+            .split_once('/')
+            .ok_or(NotationError::Syntax("piece counts"))?;
+
+        let game = GameState {
+            prot: parse_team(rules, prot_track, prot_counts, 'P')?,
+            opp: parse_team(rules, opp_track, opp_counts, 'O')?,
+            turn: match turn {
+                Some(turn) => turn.parse().map_err(|_| NotationError::Syntax("turn"))?,
+                None => 0,
+            },
+        };
+
+        game.validate(rules).map_err(NotationError::Position)?;
+        let canonical = game.notation(rules);
+        if fields.join(" ") != canonical {
+            return Err(NotationError::NotCanonical(canonical));
+        }
+        Ok(game)
+    }
+}
+
+fn track_notation(rules: &RuleSet, strip: StripState, piece: char) -> String {
+    let mut notation = String::new();
+    let mut empty = 0;
+    for i in rules.board.indices() {
+        if strip.get(i) {
+            if empty > 0 {
+                notation += &empty.to_string();
+                empty = 0;
+            }
+            notation.push(piece);
+        } else {
+            empty += 1;
+        }
+    }
+    if empty > 0 {
+        notation += &empty.to_string();
+    }
+    notation
+}
+
+fn parse_team(
+    rules: &RuleSet,
+    track: &str,
+    counts: &str,
+    piece: char,
+) -> Result<TeamState, NotationError> {
+    let mut strip = StripState::new();
+    let mut len: u32 = 0;
+    let mut empty: Option<u32> = None;
+    for c in track.chars() {
+        if let Some(digit) = c.to_digit(10) {
+            if empty.is_none() && digit == 0 {
+                return Err(NotationError::EmptyRun);
+            }
+            let run = empty.unwrap_or(0) * 10 + digit;
+            if run > rules.board.track_len() as u32 {
+                return Err(NotationError::TrackLength);
+            }
+            empty = Some(run);
+            continue;
+        }
+        len += empty.take().unwrap_or(0);
+        if c != piece {
+            return Err(NotationError::UnexpectedChar(c));
+        }
+        let i = rules
+            .board
+            .index(len as u8)
+            .ok_or(NotationError::TrackLength)?;
+        strip.set(i, true);
+        len += 1;
+    }
+    if len + empty.unwrap_or(0) != rules.board.track_len() as u32 {
+        return Err(NotationError::TrackLength);
+    }
+
+    let (waiting, score) = counts
+        .split_once(':')
+        .ok_or(NotationError::Syntax("piece counts"))?;
+    let waiting: u8 = waiting
+        .parse()
+        .map_err(|_| NotationError::Syntax("waiting pieces"))?;
+    let score: u8 = score.parse().map_err(|_| NotationError::Syntax("score"))?;
+    let total = waiting as u32 + score as u32 + strip.count_pieces() as u32;
+    if total != rules.pieces as u32 {
+        return Err(NotationError::PieceCount { piece, total });
+    }
+    Ok(TeamState { strip, score })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotationError {
+    /// wrong number of space separated fields
+    FieldCount,
+    /// a field that couldn't be read
+    Syntax(&'static str),
+    UnexpectedChar(char),
+    /// a run of no empty squares, or one with a leading zero
+    EmptyRun,
+    /// a track doesn't cover exactly the board's squares
+    TrackLength,
+    /// a team's pieces on the track, waiting and borne off don't add up to the rules' pieces
+    PieceCount {
+        piece: char,
+        total: u32,
+    },
+    /// the state can't come up in a game
+    Position(Vec<PositionError>),
+    /// the position is written differently by `notation`, which is given
+    NotCanonical(String),
+}
+
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FieldCount => write!(f, "expected tracks, piece counts and a turn if limited"),
+            Self::Syntax(field) => write!(f, "couldn't read the {field}"),
+            Self::UnexpectedChar(c) => write!(f, "unexpected {c:?} in a track"),
+            Self::EmptyRun => write!(f, "a run of empty squares starts with 0"),
+            Self::TrackLength => write!(f, "a track doesn't match the board length"),
+            Self::PieceCount { piece, total } => {
+                write!(f, "team {piece} has {total} pieces in total")
+            }
+            Self::Position(errors) => write!(f, "{}", errors.iter().join(", ")),
+            Self::NotCanonical(canonical) => write!(f, "the position is written {canonical}"),
+        }
+    }
+}
+
+impl std::error::Error for NotationError {}
+
+/// Move notation, e.g. `L→2` for launching a piece to square 2, `6→9x` for a capture and
+/// `12→off` for bearing off, or `pass` when no piece can move. `->` can be typed for `→`.
+/// When parsing, either side of the arrow can be left out as long as only one legal move fits.
+impl MoveRecord {
+    pub fn notation(&self) -> String {
+        let Some(piece) = self.piece else {
+            return "pass".to_string();
+        };
+        let source = match piece.source {
+            MoveSource::Launch => "L".to_string(),
+            MoveSource::Index(i) => i.0.to_string(),
+        };
+        let dest = match piece.dest {
+            Destination::Index(i) => i.0.to_string(),
+            Destination::BearOff => "off".to_string(),
+        };
+        let capture = if piece.capture { "x" } else { "" };
+        format!("{source}→{dest}{capture}")
+    }
+
+    pub fn from_notation(
+        notation: &str,
+        rules: &RuleSet,
+        game: GameState,
+        roll: Roll,
+    ) -> Result<Self, MoveNotationError> {
+        let notation = notation.trim();
+        let mut moves = PossibleMovesIter::new(rules, game, roll);
+        if notation == "pass" {
+            return moves
+                .find(MoveRecord::is_pass)
+                .ok_or(MoveNotationError::Illegal);
+        }
+
+        let (source, dest) = match notation.split_once('→') {
+            Some(split) => split,
+            None => notation.split_once("->").unwrap_or((notation, "")),
+        };
+        let (dest, capture) = match dest.strip_suffix('x') {
+            Some(dest) => (dest, true),
+            None => (dest, false),
+        };
+        let source = match source {
+            "" => None,
+            "L" => Some(MoveSource::Launch),
+            i => Some(MoveSource::Index(StripIndex(
+                i.parse().map_err(|_| MoveNotationError::Syntax)?,
+            ))),
+        };
+        let dest = match dest {
+            "" => None,
+            "off" => Some(Destination::BearOff),
+            i => Some(Destination::Index(StripIndex(
+                i.parse().map_err(|_| MoveNotationError::Syntax)?,
+            ))),
+        };
+        if source.is_none() && dest.is_none() {
+            return Err(MoveNotationError::Syntax);
+        }
+
+        let matching: Vec<_> = moves
+            .filter(|mov| {
+                mov.piece.is_some_and(|piece| {
+                    source.is_none_or(|source| source == piece.source)
+                        && dest.is_none_or(|dest| dest == piece.dest)
+                        && (!capture || piece.capture)
+                })
+            })
+            .collect();
+        match &matching[..] {
+            [] => Err(MoveNotationError::Illegal),
+            [mov] => Ok(mov.clone()),
+            _ => Err(MoveNotationError::Ambiguous(
+                matching.iter().map(MoveRecord::notation).collect(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoveNotationError {
+    Syntax,
+    /// no legal move with the roll fits the notation
+    Illegal,
+    /// the notation of every legal move that fits
+    Ambiguous(Vec<String>),
+}
+
+impl fmt::Display for MoveNotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Syntax => write!(f, "expected a move like L→2, 6→9x, 12→off or pass"),
+            Self::Illegal => write!(f, "no legal move fits"),
+            Self::Ambiguous(moves) => write!(f, "could be any of {}", moves.join(", ")),
+        }
+    }
+}
+
+impl std::error::Error for MoveNotationError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{rules::TurnLimit, Move};
+
+    /// positions along a game that cycles through the rolls and alternates between the first
+    /// and last legal move
+    fn positions(rules: &RuleSet) -> Vec<GameState> {
+        let rolls: Vec<_> = rules.dice.rolls().collect();
+        let mut game = GameState::new();
+        let mut positions = vec![game];
+        for ply in 0..40 {
+            let moves: Vec<_> =
+                PossibleMovesIter::new(rules, game, rolls[ply % rolls.len()]).collect();
+            let mov = if ply % 2 == 0 {
+                moves.first()
+            } else {
+                moves.last()
+            };
+            match mov.unwrap().result {
+                Move::Continue {
+                    game: next,
+                    keep_turn,
+                } => {
+                    game = if keep_turn { next } else { next.flipped() };
+                }
+                Move::End(_) => break,
+            }
+            positions.push(game);
+        }
+        positions
+    }
+
+    #[test]
+    fn positions_round_trip() {
+        let limited = RuleSet::finkel().with_turn_limit(TurnLimit::parse("60"));
+        let masters = RuleSet {
+            pieces: 3,
+            ..RuleSet::masters()
+        };
+        for rules in [RuleSet::finkel(), limited, masters] {
+            for game in positions(&rules) {
+                let notation = game.notation(&rules);
+                assert_eq!(GameState::from_notation(&notation, &rules), Ok(game));
+            }
+        }
+    }
+
+    #[test]
+    fn turn_is_written_with_a_turn_limit() {
+        let rules = RuleSet::finkel().with_turn_limit(TurnLimit::parse("60"));
+        let game = GameState {
+            turn: 12,
+            ..GameState::new()
+        };
+        assert_eq!(game.notation(&rules), "14/14 7:0/7:0 12");
+        assert_eq!(
+            GameState::from_notation("14/14 7:0/7:0 12", &rules),
+            Ok(game)
+        );
+    }
+
+    #[test]
+    fn other_spellings_are_rejected() {
+        let rules = RuleSet::finkel();
+        assert_eq!(
+            GameState::from_notation("P0P12/14 5:0/7:0", &rules),
+            Err(NotationError::EmptyRun)
+        );
+        assert_eq!(
+            GameState::from_notation("P013/14 6:0/7:0", &rules),
+            Err(NotationError::EmptyRun)
+        );
+        assert_eq!(
+            GameState::from_notation("P13/14 06:0/7:0", &rules),
+            Err(NotationError::NotCanonical("P13/14 6:0/7:0".to_string()))
+        );
+    }
+
+    fn roll(roll: u8) -> Roll {
+        Roll::new(roll).unwrap()
+    }
+
+    fn game_with(prot: &[u8], opp: &[u8]) -> GameState {
+        let mut game = GameState::new();
+        for &i in prot {
+            game.prot.strip.set(StripIndex(i), true);
+        }
+        for &i in opp {
+            game.opp.strip.set(StripIndex(i), true);
+        }
+        game
+    }
+
+    #[test]
+    fn moves_round_trip() {
+        let rules = RuleSet::finkel();
+        for game in positions(&rules) {
+            for roll in rules.dice.rolls() {
+                for mov in PossibleMovesIter::new(&rules, game, roll) {
+                    let notation = mov.notation();
+                    assert_eq!(
+                        MoveRecord::from_notation(&notation, &rules, game, roll),
+                        Ok(mov)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn move_spellings() {
+        let rules = RuleSet::finkel();
+        let parse = |notation, game, n| MoveRecord::from_notation(notation, &rules, game, roll(n));
+
+        let start = GameState::new();
+        let launch = parse("L→3", start, 4).unwrap();
+        assert_eq!(launch.notation(), "L→3");
+        assert_eq!(parse("L->3", start, 4), Ok(launch.clone()));
+        assert_eq!(parse("L", start, 4), Ok(launch.clone()));
+        assert_eq!(parse("→3", start, 4), Ok(launch));
+
+        let game = game_with(&[4], &[6]);
+        let capture = parse("4→6x", game, 2).unwrap();
+        assert!(capture.is_capture());
+        assert_eq!(parse("4→6", game, 2), Ok(capture));
+
+        let bear_off = parse("13→off", game_with(&[13], &[]), 1).unwrap();
+        assert_eq!(
+            bear_off.piece.map(|piece| piece.dest),
+            Some(Destination::BearOff)
+        );
+
+        assert!(parse("pass", start, 0).unwrap().is_pass());
+        // there is only a pass when no piece can move
+        assert_eq!(parse("pass", start, 2), Err(MoveNotationError::Illegal));
+        let one_piece = RuleSet::with_pieces(1).unwrap();
+        let stuck = MoveRecord::from_notation("pass", &one_piece, game_with(&[13], &[]), roll(2));
+        assert!(stuck.unwrap().is_pass());
+    }
+
+    #[test]
+    fn move_errors() {
+        let rules = RuleSet::finkel();
+        let start = GameState::new();
+        let parse = |notation| MoveRecord::from_notation(notation, &rules, start, roll(2));
+        assert_eq!(parse("5→7"), Err(MoveNotationError::Illegal));
+        assert_eq!(parse("L→3"), Err(MoveNotationError::Illegal));
+        // a capture where there's nothing to capture
+        assert_eq!(parse("L→1x"), Err(MoveNotationError::Illegal));
+        for notation in ["", "→", "x→1", "L→y", "L→1→2"] {
+            assert_eq!(
+                parse(notation),
+                Err(MoveNotationError::Syntax),
+                "{notation}"
+            );
+        }
+
+        // with overshooting, any piece close enough to the end bears off
+        let rules = RuleSet::finkel().with_exact_bear_off(false);
+        let game = game_with(&[12, 13], &[]);
+        assert_eq!(
+            MoveRecord::from_notation("→off", &rules, game, roll(4)),
+            Err(MoveNotationError::Ambiguous(vec![
+                "12→off".to_string(),
+                "13→off".to_string()
+            ]))
+        );
+    }
+}
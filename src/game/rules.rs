@@ -0,0 +1,303 @@
+use crate::game::{board::Board, dice::DiceModel, GameState, Outcome};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RuleSet {
+    pub pieces: u8,
+    pub board: Board,
+    /// whether a piece on a rosette is safe from capture
+    pub safe_rosettes: bool,
+    pub extra_turn: ExtraTurn,
+    pub dice: DiceModel,
+    /// whether bearing off needs the exact roll, otherwise any roll that reaches past the end
+    /// of the track bears off
+    pub exact_bear_off: bool,
+    pub turn_limit: Option<TurnLimit>,
+}
+
+/// ends the game once this many turns have passed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TurnLimit {
+    pub turns: u8,
+    pub scoring: LimitScoring,
+}
+
+/// how a game that reaches the turn limit is scored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LimitScoring {
+    Draw,
+    /// whoever has borne off more pieces wins, equal scores draw
+    BorneOff,
+}
+
+impl TurnLimit {
+    /// parses `N` or `N:borne-off`
+    pub fn parse(spec: &str) -> Option<Self> {
+        let (turns, scoring) = match spec.split_once(':') {
+            Some((turns, "borne-off")) => (turns, LimitScoring::BorneOff),
+            Some(_) => return None,
+            None => (spec, LimitScoring::Draw),
+        };
+        match turns.parse().ok()? {
+            0 => None,
+            turns => Some(TurnLimit { turns, scoring }),
+        }
+    }
+
+    /// the form accepted by `parse`
+    pub fn spec(&self) -> String {
+        match self.scoring {
+            LimitScoring::Draw => self.turns.to_string(),
+            LimitScoring::BorneOff => format!("{}:borne-off", self.turns),
+        }
+    }
+
+    /// the outcome for prot when the limit is reached in this state
+    pub fn outcome(&self, game: &GameState) -> Outcome {
+        match self.scoring {
+            LimitScoring::Draw => Outcome::Draw,
+            LimitScoring::BorneOff => match game.prot.score.cmp(&game.opp.score) {
+                std::cmp::Ordering::Greater => Outcome::Win,
+                std::cmp::Ordering::Equal => Outcome::Draw,
+                std::cmp::Ordering::Less => Outcome::Loss,
+            },
+        }
+    }
+}
+
+/// which rosettes give another turn when landed on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExtraTurn {
+    All,
+    Private,
+    Never,
+}
+
+impl ExtraTurn {
+    pub fn on_rosette(&self, shared: bool) -> bool {
+        match self {
+            Self::All => true,
+            Self::Private => !shared,
+            Self::Never => false,
+        }
+    }
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        Self::finkel()
+    }
+}
+
+impl RuleSet {
+    // scores are packed into 3 bits in GameStateSmall
+    pub const MAX_PIECES: u8 = 7;
+
+    pub fn finkel() -> Self {
+        Self {
+            pieces: Self::MAX_PIECES,
+            board: Board::finkel(),
+            safe_rosettes: true,
+            extra_turn: ExtraTurn::All,
+            dice: DiceModel::default(),
+            exact_bear_off: true,
+            turn_limit: None,
+        }
+    }
+
+    /// the Masters rules: the Masters path, three binary dice where rolling no marks moves 4,
+    /// and rosettes that don't protect from capture
+    pub fn masters() -> Self {
+        Self {
+            board: Board::masters(),
+            safe_rosettes: false,
+            dice: DiceModel::binary_zero_max(3).unwrap(),
+            ..Self::finkel()
+        }
+    }
+
+    /// these rules with the Masters path, dice and rosettes
+    pub fn with_masters(self) -> Self {
+        let masters = Self::masters();
+        self.with_board(masters.board)
+            .with_safe_rosettes(masters.safe_rosettes)
+            .with_dice(masters.dice)
+    }
+
+    pub fn with_pieces(pieces: u8) -> Option<Self> {
+        if !(1..=Self::MAX_PIECES).contains(&pieces) {
+            None
+        } else {
+            Some(Self {
+                pieces,
+                ..Self::finkel()
+            })
+        }
+    }
+
+    pub fn with_board(self, board: Board) -> Self {
+        Self { board, ..self }
+    }
+
+    pub fn with_safe_rosettes(self, safe_rosettes: bool) -> Self {
+        Self {
+            safe_rosettes,
+            ..self
+        }
+    }
+
+    pub fn with_extra_turn(self, extra_turn: ExtraTurn) -> Self {
+        Self { extra_turn, ..self }
+    }
+
+    pub fn with_dice(self, dice: DiceModel) -> Self {
+        Self { dice, ..self }
+    }
+
+    pub fn with_exact_bear_off(self, exact_bear_off: bool) -> Self {
+        Self {
+            exact_bear_off,
+            ..self
+        }
+    }
+
+    pub fn with_turn_limit(self, turn_limit: Option<TurnLimit>) -> Self {
+        Self { turn_limit, ..self }
+    }
+
+    /// parses `[pieces] [--masters] [--capture-rosettes | --safe-rosettes] [--no-extra-turn |
+    /// --private-extra-turn] [--overshoot] [--dice=SPEC] [--turn-limit=N[:borne-off]]`, where
+    /// `--masters` switches to the Masters path, dice and rosettes and the other flags change
+    /// the rules they come after
+    pub fn from_args(args: impl IntoIterator<Item = impl AsRef<str>>) -> Result<Self, String> {
+        args.into_iter()
+            .try_fold(Self::finkel(), |rules, arg| match arg.as_ref() {
+                "--masters" => Ok(rules.with_masters()),
+                "--capture-rosettes" => Ok(rules.with_safe_rosettes(false)),
+                "--safe-rosettes" => Ok(rules.with_safe_rosettes(true)),
+                "--no-extra-turn" => Ok(rules.with_extra_turn(ExtraTurn::Never)),
+                "--private-extra-turn" => Ok(rules.with_extra_turn(ExtraTurn::Private)),
+                "--overshoot" => Ok(rules.with_exact_bear_off(false)),
+                dice if dice.starts_with("--dice=") => {
+                    let spec = &dice["--dice=".len()..];
+                    let dice = DiceModel::parse(spec).ok_or(format!("invalid dice {spec}"))?;
+                    Ok(rules.with_dice(dice))
+                }
+                limit if limit.starts_with("--turn-limit=") => {
+                    let spec = &limit["--turn-limit=".len()..];
+                    let limit =
+                        TurnLimit::parse(spec).ok_or(format!("invalid turn limit {spec}"))?;
+                    Ok(rules.with_turn_limit(Some(limit)))
+                }
+                pieces => match pieces.parse() {
+                    Ok(pieces) if (1..=Self::MAX_PIECES).contains(&pieces) => {
+                        Ok(Self { pieces, ..rules })
+                    }
+                    _ => Err(format!(
+                        "pieces must be 1..={}, got {pieces}",
+                        Self::MAX_PIECES
+                    )),
+                },
+            })
+    }
+
+    /// the arguments `from_args` turns back into these rules, None if the board is neither
+    /// the finkel nor the Masters board
+    pub fn args(&self) -> Option<Vec<String>> {
+        let mut args = vec![self.pieces.to_string()];
+        let base = if self.board == Board::masters() {
+            args.push("--masters".to_string());
+            Self::masters()
+        } else if self.board == Board::finkel() {
+            Self::finkel()
+        } else {
+            return None;
+        };
+        match (self.safe_rosettes, base.safe_rosettes) {
+            (false, true) => args.push("--capture-rosettes".to_string()),
+            (true, false) => args.push("--safe-rosettes".to_string()),
+            _ => {}
+        }
+        match self.extra_turn {
+            ExtraTurn::All => {}
+            ExtraTurn::Private => args.push("--private-extra-turn".to_string()),
+            ExtraTurn::Never => args.push("--no-extra-turn".to_string()),
+        }
+        if !self.exact_bear_off {
+            args.push("--overshoot".to_string());
+        }
+        if self.dice != base.dice {
+            args.push(format!("--dice={}", self.dice.spec()));
+        }
+        if let Some(limit) = self.turn_limit {
+            args.push(format!("--turn-limit={}", limit.spec()));
+        }
+        Some(args)
+    }
+
+    /// used to name the order and vals files of this rule set
+    pub fn key(&self) -> String {
+        let mut key = format!("{}", self.pieces);
+        if self.board != Board::finkel() {
+            key += &format!("_{}", self.board.key());
+        }
+        if !self.safe_rosettes {
+            key += "_capture-rosettes";
+        }
+        match self.extra_turn {
+            ExtraTurn::All => {}
+            ExtraTurn::Private => key += "_private-extra-turn",
+            ExtraTurn::Never => key += "_no-extra-turn",
+        }
+        if self.dice != DiceModel::default() {
+            key += &format!("_dice-{}", self.dice.key());
+        }
+        if !self.exact_bear_off {
+            key += "_overshoot";
+        }
+        if let Some(limit) = self.turn_limit {
+            key += &format!("_limit{}", limit.turns);
+            if limit.scoring == LimitScoring::BorneOff {
+                key += "-borne-off";
+            }
+        }
+        key
+    }
+}
+
+/// Gives the two sides different piece counts or a head start. A side with fewer pieces plays
+/// exactly like a side that has already borne the missing pieces off, so a handicap game is the
+/// symmetric game started from a position with scores, and is solved from that position with
+/// `solve_from`. That doesn't hold when a turn limit is scored by pieces borne off, which would
+/// count the missing pieces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Handicap {
+    /// pieces of the side that moves first and of the side that moves second
+    pub pieces: [u8; 2],
+    /// pieces each side starts with already borne off
+    pub head_start: [u8; 2],
+}
+
+impl Handicap {
+    /// the starting position, with the side that moves first as prot. None for piece counts the
+    /// rules don't have, or that differ from the rules' under borne off turn limit scoring
+    pub fn start(&self, rules: &RuleSet) -> Option<GameState> {
+        let scored_by_borne_off = rules
+            .turn_limit
+            .is_some_and(|limit| limit.scoring == LimitScoring::BorneOff);
+        if scored_by_borne_off && self.pieces != [rules.pieces; 2] {
+            return None;
+        }
+        let score = |side: usize| {
+            let pieces = self.pieces[side];
+            if pieces == 0 || pieces > rules.pieces || self.head_start[side] >= pieces {
+                None
+            } else {
+                Some(rules.pieces - pieces + self.head_start[side])
+            }
+        };
+        let mut game = GameState::new();
+        game.prot.score = score(0)?;
+        game.opp.score = score(1)?;
+        Some(game)
+    }
+}
@@ -1,4 +1,7 @@
-use crate::successor::Succ;
+use crate::{
+    game::{board::Board, rules::RuleSet},
+    successor::Succ,
+};
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct StripState(pub u16);
@@ -28,18 +31,25 @@ impl StripState {
         StripIndex::succ_iter().filter(|&i| self.get(i)).count() as u8
     }
 
-    pub fn from_start_and_end(start: u8, end: u8) -> Self {
-        let start_bits = (start & 0b1111) as u16; // bits 0..=3
-        let end_bits = ((end & 0b11) as u16) << 12; // bits 12..=13
-        Self(start_bits | end_bits)
+    // shifted in u32, as a 16 square track with no private end shifts by the full 16 bits
+    pub fn from_start_and_end(board: &Board, start: u8, end: u8) -> Self {
+        let start_bits = start as u32 & Self::mask(board.start_len());
+        let end_bits =
+            (end as u32 & Self::mask(board.end_len())) << (board.track_len() - board.end_len());
+        Self((start_bits | end_bits) as u16)
     }
 
-    pub fn start_bits(&self) -> u8 {
-        ((self.0) & 0b0000_0000_0000_1111) as u8
+    pub fn start_bits(&self, board: &Board) -> u8 {
+        (self.0 as u32 & Self::mask(board.start_len())) as u8
     }
 
-    pub fn end_bits(&self) -> u8 {
-        ((self.0 >> 12) & 0b11) as u8
+    pub fn end_bits(&self, board: &Board) -> u8 {
+        ((self.0 as u32 >> (board.track_len() - board.end_len())) & Self::mask(board.end_len()))
+            as u8
+    }
+
+    fn mask(len: u8) -> u32 {
+        (1u32 << len) - 1
     }
 }
 
@@ -47,8 +57,11 @@ impl StripState {
 pub struct Delta(u8);
 
 impl Delta {
+    // a piece can't move further than the longest track
+    pub const MAX: u8 = StripIndex::MAX_TRACK_LEN;
+
     pub fn new(d: u8) -> Option<Delta> {
-        if !(1..=4).contains(&d) {
+        if !(1..=Self::MAX).contains(&d) {
             None
         } else {
             Some(Delta(d))
@@ -60,49 +73,47 @@ impl Delta {
     }
 }
 
-impl Succ for Delta {
-    fn first() -> Self {
-        Self(1)
-    }
-
-    fn succ(&self) -> Option<Self> {
-        Self::new(self.0 + 1)
-    }
-}
-
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct StripIndex(pub u8);
 
 impl StripIndex {
+    // a StripState has one bit per square
+    pub const MAX_TRACK_LEN: u8 = 16;
+
     pub fn new(i: u8) -> Option<StripIndex> {
-        if i > 13 {
+        if i >= Self::MAX_TRACK_LEN {
             None
         } else {
             Some(StripIndex(i))
         }
     }
 
-    pub fn both_teams_accessible(&self) -> bool {
-        (4..=11).contains(&self.0)
+    pub fn both_teams_accessible(&self, board: &Board) -> bool {
+        board.is_shared(*self)
     }
 
-    pub fn apply_delta(self, d: Delta) -> DeltaResult {
-        let new_i = self.0 + d.0;
-        match new_i {
-            i @ 0..=13 => DeltaResult::Index(StripIndex(i)),
-            14 => DeltaResult::Score,
-            _ => DeltaResult::OutOfBounds,
-        }
+    pub fn apply_delta(self, d: Delta, rules: &RuleSet) -> DeltaResult {
+        Self::from_track_pos(self.0 + d.0, rules)
     }
 
-    fn from_delta(d: Delta) -> StripIndex {
-        Self(d.0 - 1)
+    fn from_delta(d: Delta, rules: &RuleSet) -> DeltaResult {
+        Self::from_track_pos(d.0 - 1, rules)
     }
 
-    pub fn square(&self) -> Square {
-        match self {
-            StripIndex(3 | 7 | 13) => Square::Flower,
-            _ => Square::Normal,
+    fn from_track_pos(pos: u8, rules: &RuleSet) -> DeltaResult {
+        match pos.cmp(&rules.board.track_len()) {
+            std::cmp::Ordering::Less => DeltaResult::Index(StripIndex(pos)),
+            std::cmp::Ordering::Equal => DeltaResult::Score,
+            std::cmp::Ordering::Greater if rules.exact_bear_off => DeltaResult::OutOfBounds,
+            std::cmp::Ordering::Greater => DeltaResult::Score,
+        }
+    }
+
+    pub fn square(&self, board: &Board) -> Square {
+        if board.is_rosette(*self) {
+            Square::Flower
+        } else {
+            Square::Normal
         }
     }
 }
@@ -116,17 +127,17 @@ impl Succ for StripIndex {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum MoveSource {
     Launch,
     Index(StripIndex),
 }
 
 impl MoveSource {
-    pub fn apply_delta(self, d: Delta) -> DeltaResult {
+    pub fn apply_delta(self, d: Delta, rules: &RuleSet) -> DeltaResult {
         match self {
-            Self::Index(i) => i.apply_delta(d),
-            Self::Launch => DeltaResult::Index(StripIndex::from_delta(d)),
+            Self::Index(i) => i.apply_delta(d, rules),
+            Self::Launch => StripIndex::from_delta(d, rules),
         }
     }
 }
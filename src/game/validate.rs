@@ -0,0 +1,248 @@
+use std::fmt;
+
+use crate::game::{rules::RuleSet, strip::StripIndex, GameState, GameStateSmall, Player};
+
+/// something that makes a `GameState` impossible under a rule set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionError {
+    /// both teams have a piece on this shared square, by prot's index
+    Overlap(StripIndex),
+    /// a piece past the end of the board's track
+    OffTrack {
+        player: Player,
+        index: StripIndex,
+    },
+    /// more pieces on the track and borne off than the rules give a team
+    TooManyPieces {
+        player: Player,
+        count: u8,
+    },
+    /// a team has already borne off all its pieces, so the game is over
+    GameOver(Player),
+    PastTurnLimit {
+        turn: u8,
+    },
+    /// turns are only counted when the rules have a turn limit
+    UnexpectedTurn {
+        turn: u8,
+    },
+}
+
+impl fmt::Display for PositionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Overlap(i) => write!(f, "both teams have a piece on square {}", i.0),
+            Self::OffTrack { player, index } => {
+                write!(
+                    f,
+                    "{player:?} has a piece on square {} past the track",
+                    index.0
+                )
+            }
+            Self::TooManyPieces { player, count } => {
+                write!(
+                    f,
+                    "{player:?} has {count} pieces on the track and borne off"
+                )
+            }
+            Self::GameOver(player) => write!(f, "{player:?} has already borne off every piece"),
+            Self::PastTurnLimit { turn } => write!(f, "turn {turn} is past the turn limit"),
+            Self::UnexpectedTurn { turn } => write!(f, "turn {turn} without a turn limit"),
+        }
+    }
+}
+
+impl std::error::Error for PositionError {}
+
+impl GameState {
+    /// Checks that the state can come up in a game under the rules, listing every problem. States
+    /// that pass can be packed and looked up in a solution.
+    pub fn validate(&self, rules: &RuleSet) -> Result<(), Vec<PositionError>> {
+        let mut errors = Vec::new();
+        errors.extend(
+            rules
+                .board
+                .shared_indices()
+                .filter(|&i| {
+                    self.prot.strip.get(i)
+                        && rules
+                            .board
+                            .opponent_index(i)
+                            .is_some_and(|opp_i| self.opp.strip.get(opp_i))
+                })
+                .map(PositionError::Overlap),
+        );
+        for (player, team) in [(Player::Prot, &self.prot), (Player::Opp, &self.opp)] {
+            errors.extend(
+                (rules.board.track_len()..StripIndex::MAX_TRACK_LEN)
+                    .map(StripIndex)
+                    .filter(|&index| team.strip.get(index))
+                    .map(|index| PositionError::OffTrack { player, index }),
+            );
+            let count = team.strip.count_pieces().saturating_add(team.score);
+            if count > rules.pieces {
+                errors.push(PositionError::TooManyPieces { player, count });
+            } else if team.score == rules.pieces {
+                errors.push(PositionError::GameOver(player));
+            }
+        }
+        match rules.turn_limit {
+            Some(limit) if self.turn >= limit.turns => {
+                errors.push(PositionError::PastTurnLimit { turn: self.turn })
+            }
+            None if self.turn != 0 => {
+                errors.push(PositionError::UnexpectedTurn { turn: self.turn })
+            }
+            _ => {}
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// packs a state after validating it
+impl TryFrom<(GameState, &RuleSet)> for GameStateSmall {
+    type Error = Vec<PositionError>;
+
+    fn try_from((game, rules): (GameState, &RuleSet)) -> Result<Self, Self::Error> {
+        game.validate(rules)?;
+        Ok(Self::pack(game, &rules.board))
+    }
+}
+
+/// unpacks a state and validates it, for packed states read from outside the solver
+impl TryFrom<(GameStateSmall, &RuleSet)> for GameState {
+    type Error = Vec<PositionError>;
+
+    fn try_from((game, rules): (GameStateSmall, &RuleSet)) -> Result<Self, Self::Error> {
+        let game = game.unpack(&rules.board);
+        game.validate(rules)?;
+        Ok(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::rules::TurnLimit;
+
+    fn errors(game: GameState, rules: &RuleSet) -> Vec<PositionError> {
+        game.validate(rules).err().unwrap_or_default()
+    }
+
+    #[test]
+    fn start_is_valid() {
+        let rules = RuleSet::finkel();
+        assert_eq!(errors(GameState::new(), &rules), vec![]);
+        assert!(GameStateSmall::try_from((GameState::new(), &rules)).is_ok());
+    }
+
+    #[test]
+    fn overlap() {
+        let rules = RuleSet::finkel();
+        let mut game = GameState::new();
+        game.prot.strip.set(StripIndex(5), true);
+        game.opp.strip.set(StripIndex(5), true);
+        // private squares have the same index for both teams without overlapping
+        game.prot.strip.set(StripIndex(1), true);
+        game.opp.strip.set(StripIndex(1), true);
+        assert_eq!(
+            errors(game, &rules),
+            vec![PositionError::Overlap(StripIndex(5))]
+        );
+        assert_eq!(
+            game.player_at_i(&rules.board, StripIndex(5)),
+            Err(PositionError::Overlap(StripIndex(5)))
+        );
+        assert!(GameStateSmall::try_from((game, &rules)).is_err());
+    }
+
+    #[test]
+    fn off_track() {
+        let rules = RuleSet::finkel();
+        let mut game = GameState::new();
+        game.opp.strip.set(StripIndex(14), true);
+        assert_eq!(
+            errors(game, &rules),
+            vec![PositionError::OffTrack {
+                player: Player::Opp,
+                index: StripIndex(14)
+            }]
+        );
+    }
+
+    #[test]
+    fn too_many_pieces() {
+        let rules = RuleSet::with_pieces(2).unwrap();
+        let mut game = GameState::new();
+        game.prot.strip.set(StripIndex(0), true);
+        game.prot.score = 2;
+        assert_eq!(
+            errors(game, &rules),
+            vec![PositionError::TooManyPieces {
+                player: Player::Prot,
+                count: 3
+            }]
+        );
+    }
+
+    #[test]
+    fn game_over() {
+        let rules = RuleSet::with_pieces(2).unwrap();
+        let mut game = GameState::new();
+        game.opp.score = 2;
+        assert_eq!(
+            errors(game, &rules),
+            vec![PositionError::GameOver(Player::Opp)]
+        );
+    }
+
+    #[test]
+    fn turns() {
+        let game = GameState {
+            turn: 60,
+            ..GameState::new()
+        };
+        assert_eq!(
+            errors(game, &RuleSet::finkel()),
+            vec![PositionError::UnexpectedTurn { turn: 60 }]
+        );
+        let limited = RuleSet::finkel().with_turn_limit(TurnLimit::parse("60"));
+        assert_eq!(
+            errors(game, &limited),
+            vec![PositionError::PastTurnLimit { turn: 60 }]
+        );
+        let game = GameState { turn: 59, ..game };
+        assert_eq!(errors(game, &limited), vec![]);
+    }
+
+    #[test]
+    fn every_error_is_listed() {
+        let rules = RuleSet::with_pieces(2).unwrap();
+        let mut game = GameState::new();
+        game.prot.strip.set(StripIndex(6), true);
+        game.opp.strip.set(StripIndex(6), true);
+        game.opp.score = 2;
+        game.turn = 3;
+        assert_eq!(errors(game, &rules).len(), 3);
+    }
+
+    #[test]
+    fn unpacking_validates() {
+        let rules = RuleSet::with_pieces(2).unwrap();
+        let mut game = GameState::new();
+        game.prot.score = 5;
+        let packed = GameStateSmall::pack(game, &rules.board);
+        assert_eq!(
+            GameState::try_from((packed, &rules)),
+            Err(vec![PositionError::TooManyPieces {
+                player: Player::Prot,
+                count: 5
+            }])
+        );
+    }
+}
@@ -0,0 +1,14 @@
+//! Solver for the royal game of ur. The usual entry points are [`game::GameState`] and
+//! [`game::PossibleMovesIter`] for playing, [`solve::solve`], [`solve::load`] and
+//! [`solve::load_files`] for getting a solved table, and [`solve::Solution::lookup`] for
+//! evaluating a position.
+
+pub mod annotate;
+pub mod corpus;
+pub mod game;
+pub mod luck;
+pub mod record;
+pub mod render;
+mod save;
+pub mod solve;
+mod successor;
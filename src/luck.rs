@@ -0,0 +1,73 @@
+use crate::{
+    annotate::{annotate, AnnotateError, Thresholds},
+    game::absolute::Side,
+    record::GameRecord,
+    solve::Solution,
+};
+
+/// how one ply changed the mover's win probability, split into the part the dice decided and
+/// the part the move decided
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlyLuck {
+    pub side: Side,
+    pub roll: u8,
+    /// value before rolling, the sum of the roll terms
+    pub before: f64,
+    /// value of the best move with the roll
+    pub after_roll: f64,
+    /// value of the move played
+    pub after_move: f64,
+}
+
+impl PlyLuck {
+    pub fn luck(&self) -> f64 {
+        self.after_roll - self.before
+    }
+
+    /// zero for the best move, negative otherwise
+    pub fn skill(&self) -> f64 {
+        self.after_move - self.after_roll
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LuckReport {
+    pub plies: Vec<PlyLuck>,
+    /// luck of each side's own rolls, indexed by `Side::index`
+    pub luck: [f64; 2],
+    pub skill: [f64; 2],
+}
+
+impl LuckReport {
+    /// a side's luck minus the other side's, since good rolls for one side hurt the other
+    pub fn net_luck(&self, side: Side) -> f64 {
+        self.luck[side.index()] - self.luck[side.other().index()]
+    }
+}
+
+/// splits every ply of the record into luck and skill. The solution has to be solved with the
+/// record's rules
+pub fn decompose(solution: &Solution, record: &GameRecord) -> Result<LuckReport, AnnotateError> {
+    let annotations = annotate(solution, record, &Thresholds::default())?;
+    let mut report = LuckReport {
+        plies: Vec::with_capacity(annotations.len()),
+        luck: [0.0; 2],
+        skill: [0.0; 2],
+    };
+    for (i, annotation) in annotations.iter().enumerate() {
+        let terms = solution
+            .roll_terms(&record.rules, annotation.ply.game.relative)
+            .ok_or(AnnotateError::Unsolved { ply: i + 1 })?;
+        let ply = PlyLuck {
+            side: annotation.ply.game.to_move,
+            roll: annotation.ply.roll.get(),
+            before: terms.iter().map(|term| term.contribution()).sum(),
+            after_roll: annotation.best,
+            after_move: annotation.chosen,
+        };
+        report.luck[ply.side.index()] += ply.luck();
+        report.skill[ply.side.index()] += ply.skill();
+        report.plies.push(ply);
+    }
+    Ok(report)
+}
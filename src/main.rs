@@ -1,36 +1,205 @@
-use std::io;
+use std::path::Path;
 
-use crate::{game::GameState, render::render, solve::solve};
+use ur_solution::{
+    annotate::{annotate, format_annotations, Thresholds},
+    corpus::{analyze_dir, player_names},
+    game::{
+        absolute::{AbsoluteMove, Side},
+        rules::RuleSet,
+    },
+    luck::decompose,
+    record::GameRecord,
+    render::render_absolute,
+    solve::{load_files, load_or_solve},
+};
+
+use crate::{
+    play::{play, DiceSource},
+    query::query,
+};
 
-mod game;
 mod play;
-mod render;
-mod save;
-mod solve;
-mod successor;
+mod query;
+mod report;
+
+fn parse_rules(args: impl Iterator<Item = String>) -> RuleSet {
+    RuleSet::from_args(args).unwrap_or_else(|err| panic!("{err}"))
+}
 
-pub fn input() -> String {
-    let mut input = String::new();
-    io::stdin()
-        .read_line(&mut input)
-        .expect("Failed to read line");
-    input.trim().to_string()
+/// parses `[--inaccuracy=X] [--mistake=X] [--blunder=X]`
+fn parse_thresholds(args: impl Iterator<Item = String>) -> Thresholds {
+    args.fold(Thresholds::default(), |thresholds, arg| {
+        let (name, val) = arg
+            .split_once('=')
+            .unwrap_or_else(|| panic!("expected --name=threshold, got {arg}"));
+        let val = val
+            .parse()
+            .unwrap_or_else(|_| panic!("invalid threshold {val}"));
+        match name {
+            "--inaccuracy" => Thresholds {
+                inaccuracy: val,
+                ..thresholds
+            },
+            "--mistake" => Thresholds {
+                mistake: val,
+                ..thresholds
+            },
+            "--blunder" => Thresholds {
+                blunder: val,
+                ..thresholds
+            },
+            _ => panic!("unknown threshold {name}"),
+        }
+    })
 }
+
 fn main() {
     env_logger::init();
 
-    let (states, vals) = solve();
-    loop {
-        println!("Choose state to view: ");
-        let Ok(index) = input().parse::<usize>() else {
-            continue;
+    let mut args = std::env::args().skip(1).peekable();
+    if args.next_if_eq("report").is_some() {
+        let report = args.next();
+        let rules = parse_rules(args);
+        match report.as_deref() {
+            Some("rosettes") => println!("{}", report::rosette_capture(&rules)),
+            Some("handicap") => println!("{}", report::handicap(&rules)),
+            _ => println!("reports: rosettes, handicap"),
+        }
+        return;
+    }
+    if args.next_if_eq("replay").is_some() {
+        let path = args.next().expect("replay needs a record file");
+        let text = std::fs::read_to_string(&path).expect("failed to read record");
+        match GameRecord::parse(&text).and_then(|record| {
+            let replayed = record.replay()?;
+            Ok((record, replayed))
+        }) {
+            Ok((record, replayed)) => {
+                for ply in &replayed {
+                    println!("{}", render_absolute(&record.rules.board, &ply.game));
+                    println!("roll {}: {}", ply.roll.get(), ply.mov.notation());
+                }
+                match replayed.last().map(|ply| ply.game.apply(&ply.mov.result)) {
+                    Some(AbsoluteMove::End { side, outcome }) => {
+                        println!("ended: {outcome:?} for {side}")
+                    }
+                    _ => println!("unfinished"),
+                }
+            }
+            Err(err) => println!("invalid record: {err}"),
+        }
+        return;
+    }
+    if args.next_if_eq("annotate").is_some() {
+        let path = args.next().expect("annotate needs a record file");
+        let text = std::fs::read_to_string(&path).expect("failed to read record");
+        let record = GameRecord::parse(&text).unwrap_or_else(|err| panic!("{err}"));
+        let thresholds = parse_thresholds(args);
+        let solution = load_or_solve(&record.rules);
+        match annotate(&solution, &record, &thresholds) {
+            Ok(annotations) => println!("{}", format_annotations(&annotations)),
+            Err(err) => println!("invalid record: {err}"),
+        }
+        return;
+    }
+    if args.next_if_eq("luck").is_some() {
+        let path = args.next().expect("luck needs a record file");
+        let text = std::fs::read_to_string(&path).expect("failed to read record");
+        let record = GameRecord::parse(&text).unwrap_or_else(|err| panic!("{err}"));
+        let solution = load_or_solve(&record.rules);
+        match decompose(&solution, &record) {
+            Ok(report) => {
+                let names = player_names(&record);
+                for (i, ply) in report.plies.iter().enumerate() {
+                    println!(
+                        "{:>4}. {:<6} roll {} luck {:+.4} skill {:+.4}",
+                        i + 1,
+                        names[ply.side.index()],
+                        ply.roll,
+                        ply.luck(),
+                        ply.skill()
+                    );
+                }
+                for side in [Side::Light, Side::Dark] {
+                    println!(
+                        "{}: luck {:+.4} (net {:+.4}) skill {:+.4}",
+                        names[side.index()],
+                        report.luck[side.index()],
+                        report.net_luck(side),
+                        report.skill[side.index()]
+                    );
+                }
+            }
+            Err(err) => println!("invalid record: {err}"),
+        }
+        return;
+    }
+    if args.next_if_eq("corpus").is_some() {
+        let dir = args.next().expect("corpus needs a directory of records");
+        // solving prints progress to stdout, so the report goes to a file
+        let out = args
+            .next()
+            .expect("corpus needs a .csv or .json file to write");
+        let report = analyze_dir(Path::new(&dir), &parse_thresholds(args))
+            .expect("failed to read the records");
+        for (path, reason) in &report.skipped {
+            println!("skipped {path}: {reason}");
+        }
+        let text = if out.ends_with(".json") {
+            report.json()
+        } else {
+            report.csv()
         };
-        if index > states.len() {
-            continue;
+        std::fs::write(&out, text).expect("failed to write the report");
+        return;
+    }
+    if args.next_if_eq("play").is_some() {
+        // `--engine=first|second`, `--seed=N|random` and `--hints`, anything else is a rule
+        // argument
+        let (play_args, rule_args): (Vec<_>, Vec<_>) = args.partition(|arg| {
+            arg.starts_with("--engine=") || arg.starts_with("--seed=") || arg == "--hints"
+        });
+        let rules = parse_rules(rule_args.into_iter());
+        let mut engine_side = None;
+        let mut hints = false;
+        let mut dice = DiceSource::Typed;
+        for arg in play_args {
+            match arg.split_once('=').unwrap_or((&arg, "")) {
+                ("--hints", _) => hints = true,
+                ("--engine", "first") => engine_side = Some(Side::Light),
+                ("--engine", "second") => engine_side = Some(Side::Dark),
+                ("--seed", "random") => {
+                    let seed = rand::random();
+                    println!("seed: {seed}");
+                    dice = DiceSource::seeded(seed);
+                }
+                ("--seed", seed) => {
+                    dice = DiceSource::seeded(seed.parse().expect("seed must be a number"))
+                }
+                _ => panic!("invalid play argument {arg}"),
+            }
         }
-        let game = states[index];
-        let val = vals[index];
-        println!("{}", render(&GameState::from(game)));
-        println!("val: {val}",);
+        let solution = (hints || engine_side.is_some()).then(|| load_or_solve(&rules));
+        play(&rules, solution.as_ref(), engine_side, dice);
+        return;
     }
+    // `--order=FILE --vals=FILE [--draws=FILE]` browse a saved table, like the published ones,
+    // instead of the one in `./data`
+    let (file_args, rule_args): (Vec<_>, Vec<_>) = args.partition(|arg| {
+        arg.starts_with("--order=") || arg.starts_with("--vals=") || arg.starts_with("--draws=")
+    });
+    let rules = parse_rules(rule_args.into_iter());
+    let file = |name: &str| {
+        file_args
+            .iter()
+            .find_map(|arg| arg.strip_prefix(name)?.strip_prefix('='))
+            .map(Path::new)
+    };
+    let solution = match (file("--order"), file("--vals")) {
+        (Some(order), Some(vals)) => load_files(&rules, order, vals, file("--draws"))
+            .expect("the table files don't match the rules"),
+        (None, None) => load_or_solve(&rules),
+        _ => panic!("a table needs both --order and --vals"),
+    };
+    query(&rules, &solution);
 }
@@ -1,11 +1,27 @@
+use std::io;
+
 use itertools::Itertools;
+use rand::{rngs::StdRng, SeedableRng};
 
-use crate::{
-    game::{GameState, Move, PossibleMovesIter, Roll},
-    input,
-    render::render,
+use ur_solution::{
+    game::{
+        absolute::{AbsoluteGame, AbsoluteMove, Side},
+        rules::RuleSet,
+        MoveRecord, Roll,
+    },
+    record::{GameRecord, Ply},
+    render::render_absolute,
+    solve::Solution,
 };
 
+pub fn input() -> String {
+    let mut input = String::new();
+    io::stdin()
+        .read_line(&mut input)
+        .expect("Failed to read line");
+    input.trim().to_string()
+}
+
 fn get_input<T>(prompt: &str, mut func: impl FnMut(String) -> Option<T>) -> T {
     loop {
         println!("{prompt}");
@@ -15,21 +31,160 @@ fn get_input<T>(prompt: &str, mut func: impl FnMut(String) -> Option<T>) -> T {
     }
 }
 
-pub fn play() {
+/// where the rolls of a game come from
+pub enum DiceSource {
+    /// typed in, for games played over a real board
+    Typed,
+    Seeded(Box<StdRng>),
+}
+
+impl DiceSource {
+    pub fn seeded(seed: u64) -> Self {
+        Self::Seeded(Box::new(StdRng::seed_from_u64(seed)))
+    }
+
+    fn roll(&mut self, rules: &RuleSet) -> Roll {
+        match self {
+            Self::Typed => get_input("roll: ", |s| rules.dice.roll(s.parse().ok()?)),
+            Self::Seeded(rng) => {
+                let roll = rules.dice.sample(rng.as_mut());
+                println!("rolled {}", roll.get());
+                roll
+            }
+        }
+    }
+}
+
+/// a state a move was made from, kept so it can be undone
+struct Turn {
+    game: AbsoluteGame,
+    roll: Roll,
+}
+
+enum Action {
+    Move(MoveRecord),
+    Undo,
+}
+
+/// Plays games in the terminal. Moves are typed in move notation, except for the engine's side
+/// if there is one, which plays the best move from the solved table. With a table, `hint` ranks
+/// the moves for the roll and `prob` shows the win probability of the side to move, and `undo`
+/// takes back moves up to the last one not made by the engine.
+pub fn play(
+    rules: &RuleSet,
+    solution: Option<&Solution>,
+    engine: Option<Side>,
+    mut dice: DiceSource,
+) {
+    assert!(
+        engine.is_none() || solution.is_some(),
+        "the engine needs a solved table"
+    );
+    let solved = "states reached in play are solved";
     loop {
-        let mut game = GameState::new();
+        let mut game = AbsoluteGame::new();
+        let mut history: Vec<Turn> = Vec::new();
+        let mut undone_roll: Option<Roll> = None;
+        let mut record = GameRecord::new(rules.clone());
+        if let (Some(record), Some(engine)) = (&mut record, engine) {
+            let mut names = ["human", "human"];
+            names[engine.index()] = "engine";
+            record.set_tag("Light", names[0]);
+            record.set_tag("Dark", names[1]);
+        }
         loop {
-            println!("{}", render(&game));
-            let roll = get_input("roll: ", |s| Roll::from_index(s.parse().ok()?));
-            let moves = PossibleMovesIter::new(game, roll).collect_vec();
-            println!("moves: {moves:?}");
-            let mov = get_input("move index: ", |s| moves.get(s.parse::<usize>().ok()?));
-            match mov {
-                Move::Continue { game: new_game, .. } => {
-                    game = *new_game;
+            println!("{}", render_absolute(&rules.board, &game));
+            let roll = match undone_roll.take() {
+                Some(roll) => {
+                    println!("roll {}", roll.get());
+                    roll
+                }
+                None => dice.roll(rules),
+            };
+            let action = match (engine, solution) {
+                (Some(engine), Some(solution)) if engine == game.to_move => {
+                    let ranked = solution
+                        .ranked_moves(rules, game.relative, roll)
+                        .expect(solved);
+                    let mov = ranked[0].mov.clone();
+                    println!("engine plays {}", mov.notation());
+                    Action::Move(mov)
                 }
-                Move::End => {
-                    println!("ended");
+                _ => {
+                    let moves = game.moves(rules, roll).collect_vec();
+                    println!(
+                        "moves: {}",
+                        moves.iter().map(MoveRecord::notation).join(" ")
+                    );
+                    get_input("move (or hint, prob, undo): ", |s| {
+                        match (s.as_str(), solution) {
+                            ("undo", _) => Some(Action::Undo),
+                            ("hint" | "prob", None) => {
+                                println!("no solved table, play with --hints");
+                                None
+                            }
+                            ("hint", Some(solution)) => {
+                                for ranked in solution
+                                    .ranked_moves(rules, game.relative, roll)
+                                    .expect(solved)
+                                {
+                                    println!("{:<8} {:.4}", ranked.mov.notation(), ranked.val);
+                                }
+                                None
+                            }
+                            ("prob", Some(solution)) => {
+                                let val = solution.lookup(rules, game.relative).expect(solved);
+                                println!("win probability for {}: {val:.4}", game.to_move);
+                                None
+                            }
+                            _ => MoveRecord::from_notation(&s, rules, game.relative, roll)
+                                .inspect_err(|err| println!("{err}"))
+                                .ok()
+                                .map(Action::Move),
+                        }
+                    })
+                }
+            };
+            let mov = match action {
+                Action::Move(mov) => mov,
+                Action::Undo => {
+                    if history.is_empty() {
+                        println!("nothing to undo");
+                        undone_roll = Some(roll);
+                    }
+                    while let Some(turn) = history.pop() {
+                        if let Some(record) = &mut record {
+                            record.plies.pop();
+                        }
+                        game = turn.game;
+                        undone_roll = Some(turn.roll);
+                        if engine != Some(game.to_move) {
+                            break;
+                        }
+                    }
+                    continue;
+                }
+            };
+
+            if let (Some(engine), Some(solution)) = (engine, solution) {
+                let val = solution.move_val(rules, &mov.result).expect(solved);
+                let val = game.val_for(engine, val);
+                println!("engine win probability: {val:.4}");
+            }
+            if let Some(record) = &mut record {
+                record.plies.push(Ply {
+                    roll: roll.get(),
+                    mov: mov.notation(),
+                });
+            }
+            history.push(Turn { game, roll });
+            match game.apply(&mov.result) {
+                AbsoluteMove::Continue(next) => game = next,
+                AbsoluteMove::End { side, outcome } => {
+                    println!("ended: {outcome:?} for {side}");
+                    if let Some(record) = &record {
+                        println!("{record}");
+                    }
                     break;
                 }
             }
@@ -0,0 +1,352 @@
+use rayon::prelude::*;
+use ur_solution::{
+    game::{
+        rules::RuleSet, strip::StripIndex, GameState, GameStateSmall, Move, PossibleMovesIter,
+        Roll, TeamState,
+    },
+    render::render,
+    solve::{perma::PermaKey, query::RollTerm, Solution},
+};
+
+use crate::play::input;
+
+const HELP: &str = "\
+commands:
+  NOTATION [ROLL]   look at a position in position notation
+  INDEX [ROLL]      look at the solved state with this index
+  moves ROLL        rank the moves for a roll
+  rolls             list every roll with its probability, best move and share of the value
+  succ              rank the moves for every roll
+  go ROLL RANK      step into the position after a ranked move
+  back              step back out to the position go came from
+  pred              list the solved states with a move that leads here
+  place P|O SQUARE  put a piece of the team to move (P) or the other team (O) on a square
+  remove P|O SQUARE take a piece off a square
+  score P|O N       set how many pieces a team has borne off
+  turn N            set the turn, with a turn limit
+  swap              give the move to the other team
+  show              show the position again";
+
+enum Team {
+    Prot,
+    Opp,
+}
+
+enum Command {
+    Load(GameState, Option<Roll>),
+    Moves(Roll),
+    Rolls,
+    Succ,
+    Go(Roll, usize),
+    Back,
+    Pred,
+    Place(Team, StripIndex),
+    Remove(Team, StripIndex),
+    Score(Team, u8),
+    Turn(u8),
+    Swap,
+    Show,
+    Help,
+}
+
+fn parse_command(line: &str, rules: &RuleSet, solution: &Solution) -> Result<Command, String> {
+    let mut words = line.split_whitespace();
+    let word = words.next().unwrap_or("show");
+    let mut arg = |name: &str| words.next().ok_or_else(|| format!("{word} needs {name}"));
+    let number = |word: &str| {
+        word.parse::<u8>()
+            .map_err(|_| format!("expected a number, got {word}"))
+    };
+    let team = |word: &str| match word {
+        "P" => Ok(Team::Prot),
+        "O" => Ok(Team::Opp),
+        _ => Err(format!("expected P or O, got {word}")),
+    };
+    let square = |word: &str| {
+        rules
+            .board
+            .index(number(word)?)
+            .ok_or_else(|| format!("no square {word} on this board"))
+    };
+    let roll = |word: &str| {
+        rules
+            .dice
+            .roll(number(word)?)
+            .ok_or_else(|| format!("the dice can't roll {word}"))
+    };
+
+    let command = match word {
+        "help" => Command::Help,
+        "show" => Command::Show,
+        "swap" => Command::Swap,
+        "rolls" => Command::Rolls,
+        "succ" => Command::Succ,
+        "back" => Command::Back,
+        "pred" => Command::Pred,
+        "go" => {
+            let roll = roll(arg("a roll")?)?;
+            let rank = arg("a rank")?;
+            let rank = rank
+                .parse()
+                .map_err(|_| format!("expected a rank, got {rank}"))?;
+            Command::Go(roll, rank)
+        }
+        "moves" => Command::Moves(roll(arg("a roll")?)?),
+        "place" => Command::Place(team(arg("a team")?)?, square(arg("a square")?)?),
+        "remove" => Command::Remove(team(arg("a team")?)?, square(arg("a square")?)?),
+        "score" => Command::Score(team(arg("a team")?)?, number(arg("a score")?)?),
+        "turn" => Command::Turn(number(arg("a turn")?)?),
+        _ if line.contains('/') => {
+            let fields = if rules.turn_limit.is_some() { 3 } else { 2 };
+            let mut words = line.split_whitespace();
+            let notation = words.by_ref().take(fields).collect::<Vec<_>>().join(" ");
+            let game = GameState::from_notation(&notation, rules)
+                .map_err(|err| format!("invalid notation: {err}"))?;
+            Command::Load(game, words.next().map(roll).transpose()?)
+        }
+        index => {
+            let index = index
+                .parse::<usize>()
+                .ok()
+                .filter(|&index| index < solution.states.len())
+                .ok_or_else(|| format!("unknown command or index {index}, try help"))?;
+            let game = solution.states[index].unpack(&rules.board);
+            Command::Load(game, words.next().map(roll).transpose()?)
+        }
+    };
+    Ok(command)
+}
+
+/// the packed position, or None after printing why it's invalid
+fn pack(rules: &RuleSet, game: &GameState) -> Option<GameStateSmall> {
+    GameStateSmall::try_from((*game, rules))
+        .inspect_err(|errors| {
+            for err in errors {
+                println!("invalid: {err}");
+            }
+        })
+        .ok()
+}
+
+/// everything known about a position: the board, its encodings, and its value if it's valid
+fn show(rules: &RuleSet, solution: &Solution, game: &GameState) {
+    println!("{}", render(&rules.board, game));
+    println!("notation: {}", game.notation(rules));
+    let Some(packed) = pack(rules, game) else {
+        return;
+    };
+    println!("packed: {:#x}", u64::from(packed));
+    println!("perma key: {:?}", PermaKey::new(*game, rules));
+    let Some(index) = solution.index(rules, *game) else {
+        println!("not reachable from the start");
+        return;
+    };
+    println!("index: {index}");
+    println!("val: {}", solution.vals[index]);
+    if solution.draws.is_some() {
+        let wdl = solution.wdl(index);
+        println!(
+            "win: {:.6} draw: {:.6} loss: {:.6}",
+            wdl.win, wdl.draw, wdl.loss
+        );
+    }
+}
+
+fn show_moves(rules: &RuleSet, solution: &Solution, game: &GameState, roll: Roll) {
+    if pack(rules, game).is_none() {
+        return;
+    }
+    match solution.ranked_moves(rules, *game, roll) {
+        Some(moves) => {
+            for (rank, ranked) in moves.iter().enumerate() {
+                println!("{rank}: {:.6} {}", ranked.val, ranked.mov.notation());
+            }
+        }
+        None => println!("the position isn't solved"),
+    }
+}
+
+/// the terms of `eval_expr` for the position, which add up to its value
+fn show_rolls(rules: &RuleSet, solution: &Solution, game: &GameState) {
+    if pack(rules, game).is_none() {
+        return;
+    }
+    let Some(terms) = solution.roll_terms(rules, *game) else {
+        println!("the position isn't solved");
+        return;
+    };
+    for term in &terms {
+        println!(
+            "roll {}: prob {:.6} best {:.6} contribution {:.6}",
+            term.roll.get(),
+            term.prob,
+            term.best,
+            term.contribution()
+        );
+    }
+    let total: f64 = terms.iter().map(RollTerm::contribution).sum();
+    println!("total: {total:.6}");
+}
+
+/// the position after the move ranked `rank` with `roll`, seen from whoever moves next
+fn child(
+    rules: &RuleSet,
+    solution: &Solution,
+    game: &GameState,
+    roll: Roll,
+    rank: usize,
+) -> Result<GameState, String> {
+    pack(rules, game).ok_or("the position is invalid")?;
+    let moves = solution
+        .ranked_moves(rules, *game, roll)
+        .ok_or("the position isn't solved")?;
+    let ranked = moves
+        .get(rank)
+        .ok_or_else(|| format!("roll {} has only {} moves", roll.get(), moves.len()))?;
+    match ranked.mov.result {
+        Move::Continue {
+            game,
+            keep_turn: true,
+        } => Ok(game),
+        Move::Continue {
+            game,
+            keep_turn: false,
+        } => {
+            println!("the turn passes");
+            Ok(game.flipped())
+        }
+        Move::End(outcome) => Err(format!(
+            "{} ends the game: {outcome:?}",
+            ranked.mov.notation()
+        )),
+    }
+}
+
+/// every solved state with a move that leads to the position, going through the whole table.
+/// Moves that pass the turn lead to the flipped state, the way `go` shows it.
+fn show_preds(rules: &RuleSet, solution: &Solution, game: &GameState) {
+    if pack(rules, game).is_none() {
+        return;
+    }
+    let preds: Vec<_> = solution
+        .states
+        .par_iter()
+        .enumerate()
+        .flat_map_iter(|(index, state)| {
+            let pred = state.unpack(&rules.board);
+            rules.dice.rolls().flat_map(move |roll| {
+                PossibleMovesIter::new(rules, pred, roll)
+                    .filter(|mov| match mov.result {
+                        Move::Continue {
+                            game: next,
+                            keep_turn,
+                        } => (if keep_turn { next } else { next.flipped() }) == *game,
+                        Move::End(_) => false,
+                    })
+                    .map(move |mov| (index, pred, roll, mov))
+            })
+        })
+        .collect();
+    if preds.is_empty() {
+        println!("no solved state leads here");
+    }
+    for (index, pred, roll, mov) in preds {
+        println!(
+            "{index}: {} roll {} {}",
+            pred.notation(rules),
+            roll.get(),
+            mov.notation()
+        );
+    }
+}
+
+/// Looks positions up in a solved table. Positions are loaded by notation or index and can be
+/// edited piece by piece, with every change validated and shown along with its value. From a
+/// position the moves for every roll can be browsed and stepped into.
+pub fn query(rules: &RuleSet, solution: &Solution) {
+    let mut game = GameState::new();
+    // the positions `go` stepped out of, for `back`
+    let mut parents: Vec<GameState> = Vec::new();
+    show(rules, solution, &game);
+    println!("{HELP}");
+    loop {
+        println!("query (or help): ");
+        let line = input();
+        let command = match parse_command(&line, rules, solution) {
+            Ok(command) => command,
+            Err(err) => {
+                println!("{err}");
+                continue;
+            }
+        };
+        match command {
+            Command::Help => {
+                println!("{HELP}");
+                continue;
+            }
+            Command::Show => {}
+            Command::Load(new_game, roll) => {
+                game = new_game;
+                parents.clear();
+                show(rules, solution, &game);
+                if let Some(roll) = roll {
+                    show_moves(rules, solution, &game, roll);
+                }
+                continue;
+            }
+            Command::Moves(roll) => {
+                show_moves(rules, solution, &game, roll);
+                continue;
+            }
+            Command::Rolls => {
+                show_rolls(rules, solution, &game);
+                continue;
+            }
+            Command::Succ => {
+                for roll in rules.dice.rolls() {
+                    println!("roll {}:", roll.get());
+                    show_moves(rules, solution, &game, roll);
+                }
+                continue;
+            }
+            Command::Go(roll, rank) => match child(rules, solution, &game, roll, rank) {
+                Ok(child) => parents.push(std::mem::replace(&mut game, child)),
+                Err(err) => {
+                    println!("{err}");
+                    continue;
+                }
+            },
+            Command::Pred => {
+                show_preds(rules, solution, &game);
+                continue;
+            }
+            Command::Back => match parents.pop() {
+                Some(parent) => game = parent,
+                None => {
+                    println!("nothing to go back to");
+                    continue;
+                }
+            },
+            edit => {
+                // `back` would return to a position unrelated to the edited one
+                parents.clear();
+                match edit {
+                    Command::Place(team, i) => team_mut(&mut game, team).strip.set(i, true),
+                    Command::Remove(team, i) => team_mut(&mut game, team).strip.set(i, false),
+                    Command::Score(team, score) => team_mut(&mut game, team).score = score,
+                    Command::Turn(turn) => game.turn = turn,
+                    Command::Swap => game = game.flipped(),
+                    _ => unreachable!("every other command is handled above"),
+                }
+            }
+        }
+        show(rules, solution, &game);
+    }
+}
+
+fn team_mut(game: &mut GameState, team: Team) -> &mut TeamState {
+    match team {
+        Team::Prot => &mut game.prot,
+        Team::Opp => &mut game.opp,
+    }
+}
@@ -0,0 +1,284 @@
+use std::fmt;
+
+use crate::game::{
+    absolute::{AbsoluteGame, AbsoluteMove, Side},
+    notation::{MoveNotationError, NotationError},
+    rules::RuleSet,
+    GameState, MoveRecord, Roll,
+};
+
+/// A recorded game, written like PGN:
+///
+/// ```text
+/// [Rules "7 --capture-rosettes"]
+/// [Light "alice"]
+/// [Dark "bob"]
+///
+/// 1. 3 L→2 2. 0 pass 3. 2 2→4
+/// ```
+///
+/// The rules are written as command line arguments. Every ply is the roll followed by the move
+/// in move notation, and the plies alternate sides whenever the turn passes, starting with
+/// light. Other tags are free form metadata, except `Position`, which starts the game from a
+/// position in position notation instead of the usual start.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameRecord {
+    pub rules: RuleSet,
+    /// metadata such as players, date and result, in the order they are written
+    pub tags: Vec<(String, String)>,
+    pub plies: Vec<Ply>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ply {
+    pub roll: u8,
+    /// the move in move notation
+    pub mov: String,
+}
+
+/// a ply checked against the rules, with the state it was played from
+#[derive(Debug, Clone)]
+pub struct ReplayedPly {
+    /// the state the move was made from, with the side that made it to move
+    pub game: AbsoluteGame,
+    pub roll: Roll,
+    pub mov: MoveRecord,
+}
+
+impl GameRecord {
+    /// an empty record, None if the rules can't be written as arguments
+    pub fn new(rules: RuleSet) -> Option<Self> {
+        rules.args()?;
+        Some(Self {
+            rules,
+            tags: Vec::new(),
+            plies: Vec::new(),
+        })
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old)) => *old = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    pub fn start(&self) -> Result<GameState, RecordError> {
+        match self.tag("Position") {
+            Some(position) => {
+                GameState::from_notation(position, &self.rules).map_err(RecordError::Position)
+            }
+            None => Ok(GameState::new()),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, RecordError> {
+        let mut rules = None;
+        let mut tags = Vec::new();
+        let mut movetext = String::new();
+        for line in text.lines().map(str::trim) {
+            if let Some(tag) = line.strip_prefix('[') {
+                let (name, value) = tag
+                    .strip_suffix(']')
+                    .and_then(|tag| tag.split_once(' '))
+                    .and_then(|(name, value)| {
+                        let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+                        Some((name, unescape(value)?))
+                    })
+                    .ok_or_else(|| RecordError::Tag(line.to_string()))?;
+                if name == "Rules" {
+                    rules = Some(
+                        RuleSet::from_args(value.split_whitespace()).map_err(RecordError::Rules)?,
+                    );
+                } else {
+                    tags.push((name.to_string(), value));
+                }
+            } else {
+                movetext += line;
+                movetext += " ";
+            }
+        }
+
+        let mut plies = Vec::new();
+        let mut tokens = movetext
+            .split_whitespace()
+            .filter(|token| !is_ply_number(token) && !is_result(token));
+        while let Some(roll) = tokens.next() {
+            let roll = roll
+                .parse()
+                .map_err(|_| RecordError::Syntax(format!("expected a roll, got {roll}")))?;
+            let mov = tokens
+                .next()
+                .ok_or_else(|| RecordError::Syntax(format!("roll {roll} has no move")))?;
+            plies.push(Ply {
+                roll,
+                mov: mov.to_string(),
+            });
+        }
+
+        Ok(Self {
+            rules: rules.ok_or(RecordError::MissingRules)?,
+            tags,
+            plies,
+        })
+    }
+
+    /// plays the record through, rejecting rolls the dice can't make, illegal moves and moves
+    /// after the game has ended
+    pub fn replay(&self) -> Result<Vec<ReplayedPly>, RecordError> {
+        let mut game = Some(AbsoluteGame::from_relative(Side::Light, self.start()?));
+        let mut replayed = Vec::with_capacity(self.plies.len());
+        for (i, ply) in self.plies.iter().enumerate() {
+            let ply_number = i + 1;
+            let before = game.ok_or(RecordError::AfterEnd { ply: ply_number })?;
+            let roll = self.rules.dice.roll(ply.roll).ok_or(RecordError::Roll {
+                ply: ply_number,
+                roll: ply.roll,
+            })?;
+            let mov = MoveRecord::from_notation(&ply.mov, &self.rules, before.relative, roll)
+                .map_err(|err| RecordError::Move {
+                    ply: ply_number,
+                    mov: ply.mov.clone(),
+                    err,
+                })?;
+            game = match before.apply(&mov.result) {
+                AbsoluteMove::Continue(game) => Some(game),
+                AbsoluteMove::End { .. } => None,
+            };
+            replayed.push(ReplayedPly {
+                game: before,
+                roll,
+                mov,
+            });
+        }
+        Ok(replayed)
+    }
+}
+
+/// tag values are quoted, with `\\`, `\"` and `\n` standing for a backslash, a quote and a
+/// line break
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// None for an unknown escape or a quote that isn't escaped
+fn unescape(value: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        unescaped.push(match c {
+            '\\' => match chars.next()? {
+                'n' => '\n',
+                c @ ('\\' | '"') => c,
+                _ => return None,
+            },
+            '"' => return None,
+            c => c,
+        });
+    }
+    Some(unescaped)
+}
+
+fn is_ply_number(token: &str) -> bool {
+    token
+        .strip_suffix('.')
+        .is_some_and(|n| n.parse::<usize>().is_ok())
+}
+
+fn is_result(token: &str) -> bool {
+    matches!(token, "1-0" | "0-1" | "1/2-1/2" | "*")
+}
+
+impl fmt::Display for GameRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let args = self
+            .rules
+            .args()
+            .expect("records are only made for rules that can be written");
+        writeln!(f, "[Rules \"{}\"]", args.join(" "))?;
+        for (name, value) in &self.tags {
+            writeln!(f, "[{name} \"{}\"]", escape(value))?;
+        }
+        writeln!(f)?;
+        for (i, line) in self.plies.chunks(8).enumerate() {
+            let line: Vec<_> = line
+                .iter()
+                .enumerate()
+                .map(|(j, ply)| format!("{}. {} {}", i * 8 + j + 1, ply.roll, ply.mov))
+                .collect();
+            writeln!(f, "{}", line.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordError {
+    /// a header line that isn't `[Name "value"]`
+    Tag(String),
+    MissingRules,
+    Rules(String),
+    Position(NotationError),
+    Syntax(String),
+    /// the dice can't make this roll
+    Roll {
+        ply: usize,
+        roll: u8,
+    },
+    Move {
+        ply: usize,
+        mov: String,
+        err: MoveNotationError,
+    },
+    /// a ply after the game has already ended
+    AfterEnd {
+        ply: usize,
+    },
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tag(line) => write!(f, "invalid tag {line}"),
+            Self::MissingRules => write!(f, "missing Rules tag"),
+            Self::Rules(err) => write!(f, "invalid rules: {err}"),
+            Self::Position(err) => write!(f, "invalid position: {err}"),
+            Self::Syntax(err) => write!(f, "{err}"),
+            Self::Roll { ply, roll } => write!(f, "ply {ply}: the dice can't roll {roll}"),
+            Self::Move { ply, mov, err } => write!(f, "ply {ply}: {mov} is illegal, {err}"),
+            Self::AfterEnd { ply } => write!(f, "ply {ply}: the game has already ended"),
+        }
+    }
+}
+
+impl std::error::Error for RecordError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_round_trip() {
+        let mut record = GameRecord::new(RuleSet::finkel()).unwrap();
+        record.set_tag("Light", "Smith, \"J\"");
+        record.set_tag("Dark", "a]b\\c");
+        record.set_tag("Event", "two\nlines");
+        assert_eq!(GameRecord::parse(&record.to_string()), Ok(record));
+    }
+
+    #[test]
+    fn unescaped_quotes_are_rejected() {
+        let text = "[Rules \"7\"]\n[Light \"a\"b\"]\n";
+        assert!(matches!(GameRecord::parse(text), Err(RecordError::Tag(_))));
+    }
+}
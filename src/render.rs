@@ -1,68 +1,68 @@
 use crate::game::{
-    strip::{Square, StripIndex},
-    GameState, Player,
+    absolute::{AbsoluteGame, Side},
+    board::{Board, Coord},
+    strip::Square,
+    GameState, TeamState,
 };
 
-pub fn render(game: &GameState) -> String {
-    pub enum RowItem {
-        I(u8),
-        X,
-    }
-    use RowItem::*;
-    const SIDE_ROW: [RowItem; 8] = [I(3), I(2), I(1), I(0), X, X, I(13), I(12)];
-    const MID_ROW: [RowItem; 8] = [I(4), I(5), I(6), I(7), I(8), I(9), I(10), I(11)];
+/// draws the state with the side to move as `P` on the top row and the other side as `O`
+pub fn render(board: &Board, game: &GameState) -> String {
+    render_teams(board, [&game.prot, &game.opp], ['P', 'O'], ["to move", ""])
+}
 
-    fn draw_player(player: Option<Player>, square: Square) -> &'static str {
-        match (player, square) {
-            (Some(Player::Opp), Square::Flower) => "(O)",
-            (Some(Player::Opp), Square::Normal) => "[O]",
-            (Some(Player::Prot), Square::Flower) => "(P)",
-            (Some(Player::Prot), Square::Normal) => "[P]",
-            (None, Square::Normal) => " - ",
-            (None, Square::Flower) => " * ",
+/// draws light as `L` on the top row and dark as `D` on the bottom row, whoever is to move
+pub fn render_absolute(board: &Board, game: &AbsoluteGame) -> String {
+    let label = |side: Side| {
+        if side == game.to_move {
+            format!("{side} to move")
+        } else {
+            side.to_string()
         }
-    }
-    let side_row_prot: String = SIDE_ROW
-        .iter()
-        .map(|item| match item {
-            I(i) => {
-                let index = StripIndex::new(*i).unwrap();
-                if game.prot.strip.get(index) {
-                    draw_player(Some(Player::Prot), index.square())
-                } else {
-                    draw_player(None, index.square())
-                }
-            }
-            X => "   ",
-        })
-        .collect();
-    let side_row_opp: String = SIDE_ROW
-        .iter()
-        .map(|item| match item {
-            I(i) => {
-                let index = StripIndex::new(*i).unwrap();
-                if game.opp.strip.get(index) {
-                    draw_player(Some(Player::Opp), index.square())
-                } else {
-                    draw_player(None, index.square())
-                }
-            }
-            X => "   ",
-        })
-        .collect();
-    let mid_row: String = MID_ROW
-        .iter()
-        .map(|item| match item {
-            I(i) => {
-                let index = StripIndex::new(*i).unwrap();
-                draw_player(game.player_at_i(index), index.square())
-            }
-            X => "   ",
-        })
-        .collect();
-    let prot_score = game.prot.score;
+    };
+    render_teams(
+        board,
+        [game.team(Side::Light), game.team(Side::Dark)],
+        ['L', 'D'],
+        [label(Side::Light), label(Side::Dark)]
+            .each_ref()
+            .map(String::as_str),
+    )
+}
 
-    let opp_score = game.opp.score;
+/// draws `teams[0]` in light's place and `teams[1]` in dark's
+fn render_teams(
+    board: &Board,
+    teams: [&TeamState; 2],
+    marks: [char; 2],
+    labels: [&str; 2],
+) -> String {
+    let sides = [Side::Light, Side::Dark];
+    let draw_square = |coord: Coord| -> String {
+        let mut indices =
+            (0..2).filter_map(|team| Some((team, board.index_at(sides[team], coord)?)));
+        let Some((_, index)) = indices.clone().next() else {
+            return "   ".to_string();
+        };
+        let mark = indices
+            .find(|&(team, index)| teams[team].strip.get(index))
+            .map(|(team, _)| marks[team]);
+        match (mark, index.square(board)) {
+            (Some(mark), Square::Flower) => format!("({mark})"),
+            (Some(mark), Square::Normal) => format!("[{mark}]"),
+            (None, Square::Normal) => " - ".to_string(),
+            (None, Square::Flower) => " * ".to_string(),
+        }
+    };
+    let [top, mid, bottom] = [0, 1, 2].map(|row| {
+        (0..board.cols())
+            .map(|col| draw_square(Coord { row, col }))
+            .collect::<String>()
+    });
+    let [top_score, bottom_score] = [0, 1].map(|team| {
+        format!("score: {} {}", teams[team].score, labels[team])
+            .trim_end()
+            .to_string()
+    });
 
-    format!("{side_row_prot}   score: {prot_score} has turn \n{mid_row}\n{side_row_opp}   score: {opp_score}", )
+    format!("{top}   {top_score}\n{mid}\n{bottom}   {bottom_score}")
 }
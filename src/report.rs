@@ -0,0 +1,95 @@
+use itertools::Itertools;
+
+use ur_solution::{
+    game::{
+        rules::{Handicap, LimitScoring, RuleSet},
+        GameState,
+    },
+    solve::{solve, solve_from},
+};
+
+fn start_val(rules: &RuleSet) -> f64 {
+    solve(rules)
+        .lookup(rules, GameState::new())
+        .expect("start state is always solved")
+}
+
+/// compares the first player's chance to win with and without captures on rosettes
+pub fn rosette_capture(rules: &RuleSet) -> String {
+    let safe = start_val(&rules.clone().with_safe_rosettes(true));
+    let capture = start_val(&rules.clone().with_safe_rosettes(false));
+    format!(
+        "first player win probability with {} pieces\n\
+         safe rosettes:       {safe:.6}\n\
+         capture on rosettes: {capture:.6}\n\
+         difference:          {:+.6}",
+        rules.pieces,
+        capture - safe
+    )
+}
+
+/// the first player's chance to win for each combination of `labels`, each row is a value of
+/// `first` and each column one of `second`. Every start is solved from scratch, as the usual
+/// table can't reach starts with scores when the rules have a turn limit
+fn handicap_table(
+    rules: &RuleSet,
+    labels: impl Iterator<Item = u8> + Clone,
+    handicap: impl Fn(u8, u8) -> Handicap,
+) -> String {
+    let start = |first, second| {
+        handicap(first, second)
+            .start(rules)
+            .expect("every labelled handicap is valid")
+    };
+    let starts: Vec<_> = labels
+        .clone()
+        .cartesian_product(labels.clone())
+        .map(|(first, second)| start(first, second))
+        .collect();
+    let solution = solve_from(rules, &starts);
+    let header: String = labels.clone().map(|label| format!("{label:>9}")).collect();
+    let rows = labels.clone().map(|first| {
+        let row: String = labels
+            .clone()
+            .map(|second| {
+                let val = solution
+                    .lookup(rules, start(first, second))
+                    .expect("every start is solved");
+                format!("{val:>9.4}")
+            })
+            .collect();
+        format!("{first:>5}{row}")
+    });
+    format!("{:>5}{header}\n{}", "", rows.format("\n"))
+}
+
+/// The first player's chance to win for every combination of piece counts. A head start plays
+/// like the same number of missing pieces, except when a turn limit is scored by pieces borne
+/// off, where the pieces already borne off count and missing pieces can't be expressed, so the
+/// table is by head start instead.
+pub fn handicap(rules: &RuleSet) -> String {
+    let scored_by_borne_off = rules
+        .turn_limit
+        .is_some_and(|limit| limit.scoring == LimitScoring::BorneOff);
+    if scored_by_borne_off {
+        let head_starts = handicap_table(rules, 0..rules.pieces, |first, second| Handicap {
+            pieces: [rules.pieces; 2],
+            head_start: [first, second],
+        });
+        format!(
+            "first player win probability by pieces already borne off, with {} pieces each \
+             (rows: first player, columns: second player)\n\
+             {head_starts}",
+            rules.pieces
+        )
+    } else {
+        let pieces = handicap_table(rules, (1..=rules.pieces).rev(), |first, second| Handicap {
+            pieces: [first, second],
+            head_start: [0, 0],
+        });
+        format!(
+            "first player win probability by pieces (rows: first player, columns: second player)\n\
+             {pieces}"
+        )
+    }
+}
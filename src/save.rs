@@ -1,11 +1,13 @@
 use std::{
     fs::File,
     io::{BufReader, BufWriter, ErrorKind},
+    path::Path,
 };
 
-pub fn read<D: bincode::Decode<()>>(path: &str) -> Option<D> {
+pub fn read<D: bincode::Decode<()>>(path: impl AsRef<Path>) -> Option<D> {
+    let path = path.as_ref().display();
     println!("trying to read {path}...");
-    let file = match File::open(path) {
+    let file = match File::open(path.to_string()) {
         Ok(file) => file,
         Err(err) => match err.kind() {
             ErrorKind::NotFound => {
@@ -4,10 +4,10 @@ use num_traits::Float;
 use rayon::prelude::*;
 
 use crate::{
-    game::GameStateSmall,
+    game::{rules::RuleSet, GameStateSmall},
     solve::{
         converge_gpu::{Converger, DeviceHolder},
-        expr::{create_exprs, eval_expr, ExprPart},
+        expr::{create_exprs, eval_draw_expr, eval_expr, ExprPart},
     },
 };
 
@@ -16,13 +16,15 @@ pub const THRESHOLD_DELTA_64: f64 = 1e-15;
 pub const GPU_THRESHOLD: usize = 100_000_000;
 pub const MAX_ITERS: usize = 2000;
 
+#[allow(clippy::too_many_arguments)]
 pub fn converge(
+    rules: &RuleSet,
     states: &[GameStateSmall],
     vals: &mut [f64],
     dep_start: usize,
     start: usize,
     end: usize,
-    device_holder: &mut DeviceHolder,
+    device_holder: &mut Option<DeviceHolder>,
     expr_parts: &mut Vec<ExprPart>,
     expr_starts: &mut Vec<u32>,
 ) {
@@ -38,7 +40,17 @@ pub fn converge(
     expr_parts.clear();
     expr_starts.clear();
 
-    create_exprs(states, dep_start, start, end, expr_parts, expr_starts);
+    create_exprs(
+        rules,
+        states,
+        dep_start,
+        start,
+        end,
+        expr_parts,
+        expr_starts,
+    );
+
+    let roll_weights = rules.dice.weights();
 
     let [dep_vals, vals] = vals
         .get_disjoint_mut([dep_start..start, start..end])
@@ -56,7 +68,8 @@ pub fn converge(
             vals,
             expr_parts,
             expr_starts,
-            device_holder,
+            &roll_weights,
+            device_holder.get_or_insert_with(DeviceHolder::new),
         );
     } else {
         converge_cpu(
@@ -65,17 +78,74 @@ pub fn converge(
             vals,
             expr_parts,
             expr_starts,
+            &roll_weights,
             THRESHOLD_DELTA_64,
         );
     }
 }
 
+/// Fills in the draw probabilities of `start..end` once their vals have converged, with both
+/// players making the moves the vals pick. Uses the exprs left behind by `converge`.
+#[allow(clippy::too_many_arguments)]
+pub fn converge_draws(
+    rules: &RuleSet,
+    vals: &[f64],
+    draws: &mut [f64],
+    dep_start: usize,
+    start: usize,
+    end: usize,
+    expr_parts: &[ExprPart],
+    expr_starts: &[u32],
+) {
+    let roll_weights = rules.dice.weights();
+
+    let [dep_draws, draws] = draws
+        .get_disjoint_mut([dep_start..start, start..end])
+        .unwrap();
+    draws.fill(0.0);
+    let mut out_draws = draws.to_vec();
+
+    let mut iters = 0;
+    loop {
+        iters += 1;
+        let get_draw = |i: usize| {
+            let dep_index = i - dep_start;
+            if dep_index < dep_draws.len() {
+                dep_draws[dep_index]
+            } else {
+                draws[dep_index - dep_draws.len()]
+            }
+        };
+        out_draws.par_iter_mut().enumerate().for_each(|(i, draw)| {
+            *draw = eval_draw_expr(
+                expr_parts,
+                expr_starts[i] as usize,
+                &roll_weights,
+                |i| vals[i],
+                get_draw,
+            );
+        });
+
+        let delta = max_delta(&out_draws, draws);
+        draws.copy_from_slice(&out_draws);
+
+        if delta <= THRESHOLD_DELTA_64 {
+            break;
+        }
+        if iters > MAX_ITERS {
+            panic!("reached max iters: {MAX_ITERS}");
+        }
+    }
+}
+
+#[allow(dead_code)]
 fn converge_gpu_f32(
     dep_start: usize,
     dep_vals: &[f32],
     vals: &mut [f32],
     expr_parts: &[ExprPart],
     expr_starts: &[u32],
+    roll_weights: &[f32],
     device_holder: &mut DeviceHolder,
 ) {
     println!("using gpu...");
@@ -86,6 +156,7 @@ fn converge_gpu_f32(
         vals.len(),
         expr_parts,
         expr_starts,
+        roll_weights,
     );
     let in_vals = &mut vec![0.0; vals.len()];
     let out_vals = &mut vec![0.0; vals.len()];
@@ -101,7 +172,6 @@ fn converge_gpu_f32(
         }
         if iters > MAX_ITERS {
             panic!("reached max iters: {MAX_ITERS}");
-            break;
         }
     }
 
@@ -113,10 +183,12 @@ fn converge_gpu_f64(
     vals: &mut [f64],
     expr_parts: &[ExprPart],
     expr_starts: &[u32],
+    roll_weights: &[f64],
     device_holder: &mut DeviceHolder,
 ) {
     println!("using gpu...");
     let dep_vals_f32: Vec<_> = dep_vals.iter().map(|f| *f as f32).collect();
+    let roll_weights_f32: Vec<_> = roll_weights.iter().map(|f| *f as f32).collect();
     let mut converger = Converger::new(
         device_holder,
         dep_start,
@@ -124,6 +196,7 @@ fn converge_gpu_f64(
         vals.len(),
         expr_parts,
         expr_starts,
+        &roll_weights_f32,
     );
     let in_vals = &mut vec![0.0; vals.len()];
     let out_vals = &mut vec![0.0; vals.len()];
@@ -153,6 +226,7 @@ fn converge_gpu_f64(
         vals,
         expr_parts,
         expr_starts,
+        roll_weights,
         THRESHOLD_DELTA_64,
     );
 }
@@ -163,6 +237,7 @@ fn converge_cpu<T: Float + Send + Sync + Display>(
     vals: &mut [T],
     expr_parts: &[ExprPart],
     expr_starts: &[u32],
+    roll_weights: &[T],
     threshold_delta: T,
 ) {
     let mut out_vals: &mut [T] = &mut vals.to_vec();
@@ -178,6 +253,7 @@ fn converge_cpu<T: Float + Send + Sync + Display>(
             out_vals,
             expr_parts,
             expr_starts,
+            roll_weights,
         );
 
         let delta = max_delta(in_vals, out_vals);
@@ -188,7 +264,6 @@ fn converge_cpu<T: Float + Send + Sync + Display>(
         }
         if iters > MAX_ITERS {
             panic!("reached max iters: {MAX_ITERS}");
-            break;
         }
 
         (in_vals, out_vals) = (out_vals, in_vals);
@@ -210,6 +285,7 @@ pub fn step<T: Float + Send + Sync>(
 
     expr_parts: &[ExprPart],
     expr_starts: &[u32],
+    roll_weights: &[T],
 ) {
     let get_val = |i| {
         let dep_index = i - dep_start;
@@ -220,7 +296,7 @@ pub fn step<T: Float + Send + Sync>(
         }
     };
     out_vals.par_iter_mut().enumerate().for_each(|(i, val)| {
-        *val = eval_expr(expr_parts, expr_starts[i] as usize, get_val);
+        *val = eval_expr(expr_parts, expr_starts[i] as usize, roll_weights, get_val);
     });
 }
 
@@ -21,6 +21,9 @@ var<storage, read_write> out_vals: array<f32>;
 var<storage, read> expr_starts: array<u32>;
 @group(0) @binding(5)
 var<storage, read> expr_parts: array<u32>;
+// Probability of each roll of the dice model, in the order the expressions were created.
+@group(0) @binding(6)
+var<storage, read> roll_weights: array<f32>;
 
 
 // Ideal workgroup size depends on the hardware, the workload, and other factors. However, it should
@@ -59,11 +62,11 @@ fn eval_expr(first_part_index: u32) -> f32 {
     var current_roll: u32 = 0;
     var sum: f32 = 0.0;
     var current_max: f32 = -1.0;
-    while current_roll < 5 {
+    while current_roll < arrayLength(&roll_weights) {
         let part: u32 = expr_parts[i];
 
-        var val: f32 = 1.0;
-        if !part_is_win(part) {
+        var val: f32 = 1.0 - 0.5 * f32(get_var(part));
+        if !part_is_terminal(part) {
             val = in_vals[get_var(part)];
         }
 
@@ -74,7 +77,7 @@ fn eval_expr(first_part_index: u32) -> f32 {
         current_max = max(current_max, val);
 
         if part_is_end(part) {
-            sum += roll_weight(current_roll) * current_max;
+            sum += roll_weights[current_roll] * current_max;
 
             current_max = -1.0;
             current_roll += 1;
@@ -84,21 +87,6 @@ fn eval_expr(first_part_index: u32) -> f32 {
     return sum;
 }
 
-fn roll_weight(roll: u32) -> f32 {
-    if roll == 0 {
-        return 1.0 / 16.0;
-    } else if roll == 1 {
-        return 4.0 / 16.0;
-    } else if roll == 2 {
-        return 6.0 / 16.0;
-    } else if roll == 3 {
-        return 4.0 / 16.0;
-    } else if roll == 4 {
-        return 1.0 / 16.0;
-    }
-    return 0.0;
-}
-
 fn part_is_end(part: u32) -> bool {
     return (part & (1 << 31)) != 0;
 }
@@ -107,7 +95,8 @@ fn part_is_inverse(part: u32) -> bool {
     return (part & (1 << 30)) != 0;
 }
 
-fn part_is_win(part: u32) -> bool {
+// terminal parts hold 0 for a win, 1 for a draw and 2 for a loss in place of the var
+fn part_is_terminal(part: u32) -> bool {
     return (part & (1 << 29)) != 0;
 }
 
@@ -1,5 +1,4 @@
 use itertools::Itertools;
-use rayon::prelude::*;
 /// To serve as an introduction to the wgpu api, we will implement a simple
 /// compute shader which takes a list of numbers on the CPU and doubles them on the GPU.
 ///
@@ -60,7 +59,7 @@ impl DeviceHolder {
 
         let mut required_limits = wgpu::Limits::downlevel_defaults();
         required_limits.max_buffer_size = 8589934592;
-        required_limits.max_storage_buffers_per_shader_stage = 5;
+        required_limits.max_storage_buffers_per_shader_stage = 6;
         let required_features = wgpu::Features::empty();
         required_limits.max_storage_buffer_binding_size = u32::MAX;
         let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
@@ -137,7 +136,7 @@ impl DeviceHolder {
                     count: None,
                 },
                 wgpu::BindGroupLayoutEntry {
-                    // expr_starts
+                    // expr_parts
                     binding: 5,
                     visibility: wgpu::ShaderStages::COMPUTE,
                     ty: wgpu::BindingType::Buffer {
@@ -147,6 +146,17 @@ impl DeviceHolder {
                     },
                     count: None,
                 },
+                wgpu::BindGroupLayoutEntry {
+                    // roll_weights
+                    binding: 6,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: Some(NonZeroU64::new(4).unwrap()),
+                    },
+                    count: None,
+                },
             ],
         });
 
@@ -181,6 +191,7 @@ pub struct Converger {
     vals_buffer: wgpu::Buffer,
     expr_starts_buffer: wgpu::Buffer,
     expr_parts_buffer: wgpu::Buffer,
+    roll_weights_buffer: wgpu::Buffer,
 
     download_vals_buffer: wgpu::Buffer,
     download_old_vals_buffer: wgpu::Buffer,
@@ -199,6 +210,7 @@ impl Converger {
 
         expr_parts: &[ExprPart],
         expr_starts: &[u32],
+        roll_weights: &[f32],
     ) -> Self {
         let DeviceHolder { device, .. } = device_holder;
 
@@ -260,6 +272,12 @@ impl Converger {
             usage: wgpu::BufferUsages::STORAGE,
         });
 
+        let roll_weights_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Roll Weights Buffer"),
+            contents: bytemuck::cast_slice(roll_weights),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+
         let download_vals_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("Download Val Buffer"),
             size: val_buffer_size,
@@ -280,6 +298,7 @@ impl Converger {
             vals_buffer,
             expr_starts_buffer,
             expr_parts_buffer,
+            roll_weights_buffer,
 
             download_vals_buffer,
             download_old_vals_buffer,
@@ -302,6 +321,7 @@ impl Converger {
             old_vals_buffer,
             expr_starts_buffer,
             expr_parts_buffer,
+            roll_weights_buffer,
             workgroups_x,
             workgroups_y,
             download_old_vals_buffer,
@@ -347,6 +367,10 @@ impl Converger {
                         binding: 5,
                         resource: expr_parts_buffer.as_entire_binding(),
                     },
+                    wgpu::BindGroupEntry {
+                        binding: 6,
+                        resource: roll_weights_buffer.as_entire_binding(),
+                    },
                 ],
             });
 
@@ -1,8 +1,8 @@
 use itertools::Itertools;
 
 use crate::{
-    game::{GameState, GameStateSmall, Move, PossibleMovesIter, Roll},
-    successor::Succ,
+    game::{rules::RuleSet, GameStateSmall, Move, Outcome, PossibleMovesIter},
+    solve::order::cmp_states,
 };
 use num_traits::Float;
 use rayon::prelude::*;
@@ -21,8 +21,13 @@ impl ExprPart {
             bits |= 1 << 30;
         }
         match val {
-            Val::Win => {
+            Val::End(outcome) => {
                 bits |= 1 << 29;
+                bits |= match outcome {
+                    Outcome::Win => 0,
+                    Outcome::Draw => 1,
+                    Outcome::Loss => 2,
+                };
             }
             Val::Var(index) => {
                 let lower_29 = Self::LOWER_29_MASK & index;
@@ -41,9 +46,13 @@ impl ExprPart {
     }
 
     fn get_val(&self) -> Val {
-        let is_win = (self.0 & (1 << 29)) != 0;
-        if is_win {
-            Val::Win
+        let is_terminal = (self.0 & (1 << 29)) != 0;
+        if is_terminal {
+            Val::End(match self.0 & Self::LOWER_29_MASK {
+                0 => Outcome::Win,
+                1 => Outcome::Draw,
+                _ => Outcome::Loss,
+            })
         } else {
             Val::Var(self.0 & Self::LOWER_29_MASK)
         }
@@ -63,7 +72,8 @@ impl From<u32> for ExprPart {
 
 #[derive(Debug, Clone)]
 pub enum Val {
-    Win,
+    /// the game ends, the outcome is for the player choosing the move
+    End(Outcome),
     Var(u32),
 }
 
@@ -90,7 +100,7 @@ pub enum Val {
 //                 match mov {
 //                     Move::End => {
 //                         expr_parts.truncate(first_part_i);
-//                         expr_parts.push(ExprPart::new(true, false, Val::Win));
+//                         expr_parts.push(ExprPart::new(true, false, Val::End(Outcome::Win)));
 //                         break;
 //                     }
 //                     Move::Continue { game, keep_turn } => {
@@ -113,6 +123,7 @@ pub enum Val {
 // }
 
 pub fn create_exprs(
+    rules: &RuleSet,
     states: &[GameStateSmall],
     dep_start: usize,
     start: usize,
@@ -123,22 +134,27 @@ pub fn create_exprs(
     let parts: Vec<_> = states[start..end]
         .par_iter()
         .flat_map_iter(|game| {
-            Roll::succ_iter().flat_map(|roll| {
+            rules.dice.rolls().flat_map(|roll| {
                 let mut buf: [Option<ExprPart>; 7] = [None; 7];
                 let mut index = 0;
 
-                for mov in PossibleMovesIter::new(GameState::from(*game), roll) {
-                    match mov {
-                        Move::End => {
-                            buf[0] = Some(ExprPart::new(true, false, Val::Win));
+                for mov in PossibleMovesIter::new(rules, game.unpack(&rules.board), roll) {
+                    match mov.result {
+                        Move::End(Outcome::Win) => {
+                            buf[0] = Some(ExprPart::new(true, false, Val::End(Outcome::Win)));
                             buf[1] = None;
 
                             return buf.into_iter().while_some();
                         }
+                        Move::End(outcome) => {
+                            buf[index] = Some(ExprPart::new(false, false, Val::End(outcome)));
+                            index += 1;
+                        }
                         Move::Continue { game, keep_turn } => {
                             let game = if keep_turn { game } else { game.flipped() };
+                            let game = GameStateSmall::pack(game, &rules.board);
                             let idx: u32 = (states[dep_start..end]
-                                .binary_search(&GameStateSmall::from(game))
+                                .binary_search_by(|probe| cmp_states(rules, probe, &game))
                                 .unwrap()
                                 + dep_start)
                                 .try_into()
@@ -159,31 +175,38 @@ pub fn create_exprs(
         })
         .collect();
     expr_parts.extend(parts);
+    // a state's expression starts at the first part of its first roll, every roll ends with an
+    // end part and the first roll can have several parts
     let mut roll = 0;
+    let mut roll_start = true;
     for (i, part) in expr_parts.iter().enumerate() {
-        if roll % Roll::vals().len() == 0 {
+        if roll_start && roll % rules.dice.len() == 0 {
             expr_starts.push(i as u32);
         }
+        roll_start = part.is_end();
         if part.is_end() {
             roll += 1;
         }
     }
 }
 
+/// `roll_weights` holds the probability of each roll of the dice model, in the order the
+/// expressions were created
 pub fn eval_expr<T: Float>(
     expr_parts: &[ExprPart],
     first_part_index: usize,
+    roll_weights: &[T],
     get_val: impl Fn(usize) -> T,
 ) -> T {
     let mut i = first_part_index;
-    let mut current_roll = Some(Roll::first());
+    let mut current_roll = 0;
     let mut sum: T = T::zero();
     let mut current_max: T = T::neg_infinity();
-    while let Some(roll) = current_roll {
+    while let Some(&weight) = roll_weights.get(current_roll) {
         let part = &expr_parts[i];
 
         let val = match part.get_val() {
-            Val::Win => T::one(),
+            Val::End(outcome) => T::from(outcome.val()).unwrap(),
             Val::Var(index) => get_val(index as usize),
         };
         let val = if part.is_inverse() {
@@ -195,10 +218,61 @@ pub fn eval_expr<T: Float>(
         current_max = current_max.max(val);
 
         if part.is_end() {
-            sum = sum + roll.weight::<T>() * current_max;
+            sum = sum + weight * current_max;
+
+            current_max = T::neg_infinity();
+            current_roll += 1;
+        }
+        i += 1;
+    }
+    sum
+}
+
+/// follows the move `eval_expr` would pick for each roll, and sums the draw probability of that
+/// move instead of its value
+pub fn eval_draw_expr<T: Float>(
+    expr_parts: &[ExprPart],
+    first_part_index: usize,
+    roll_weights: &[T],
+    get_val: impl Fn(usize) -> T,
+    get_draw: impl Fn(usize) -> T,
+) -> T {
+    let mut i = first_part_index;
+    let mut current_roll = 0;
+    let mut sum: T = T::zero();
+    let mut current_max: T = T::neg_infinity();
+    let mut current_draw: T = T::zero();
+    while let Some(&weight) = roll_weights.get(current_roll) {
+        let part = &expr_parts[i];
+
+        let (val, draw) = match part.get_val() {
+            Val::End(outcome) => (
+                T::from(outcome.val()).unwrap(),
+                if outcome == Outcome::Draw {
+                    T::one()
+                } else {
+                    T::zero()
+                },
+            ),
+            Val::Var(index) => (get_val(index as usize), get_draw(index as usize)),
+        };
+        let val = if part.is_inverse() {
+            T::one() - val
+        } else {
+            val
+        };
+
+        if val > current_max {
+            current_max = val;
+            current_draw = draw;
+        }
+
+        if part.is_end() {
+            sum = sum + weight * current_draw;
 
             current_max = T::neg_infinity();
-            current_roll = roll.succ();
+            current_draw = T::zero();
+            current_roll += 1;
         }
         i += 1;
     }
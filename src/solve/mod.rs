@@ -1,9 +1,16 @@
-use std::time::Instant;
+use std::{ops::Range, path::Path, time::Instant};
+
+use rayon::prelude::*;
 
 use crate::{
-    game::{GameStateSmall, GOAL_SCORE},
+    game::{rules::RuleSet, GameState, GameStateSmall},
     save,
-    solve::{converge::converge, converge_gpu::DeviceHolder, order::get_order},
+    solve::{
+        converge::{converge, converge_draws},
+        converge_gpu::DeviceHolder,
+        order::{cmp_states, create_order_from, get_order, order_path, perma_ranges},
+        perma::PermaKey,
+    },
 };
 
 mod converge;
@@ -11,6 +18,7 @@ mod converge_gpu;
 pub mod expr;
 pub mod order;
 pub mod perma;
+pub mod query;
 mod table;
 pub mod table_gpu;
 
@@ -25,14 +33,81 @@ where
     result
 }
 
-pub fn solve() -> (Vec<GameStateSmall>, Vec<f64>) {
-    let (states, perma_keys) = get_order();
+/// the solved states, in solving order, with their values for the player to move
+pub struct Solution {
+    pub states: Vec<GameStateSmall>,
+    /// win probability, with draws counted as half a win
+    pub vals: Vec<f64>,
+    /// draw probability, only solved when the rules have a turn limit
+    pub draws: Option<Vec<f64>>,
+}
+
+/// win, draw and loss probabilities of a state
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Wdl {
+    pub win: f64,
+    pub draw: f64,
+    pub loss: f64,
+}
+
+impl Solution {
+    pub fn index(&self, rules: &RuleSet, game: GameState) -> Option<usize> {
+        let game = GameStateSmall::try_from((game, rules)).ok()?;
+        self.states
+            .binary_search_by(|probe| cmp_states(rules, probe, &game))
+            .ok()
+    }
+
+    pub fn lookup(&self, rules: &RuleSet, game: GameState) -> Option<f64> {
+        self.index(rules, game).map(|i| self.vals[i])
+    }
+
+    pub fn wdl(&self, i: usize) -> Wdl {
+        let val = self.vals[i];
+        let draw = self.draws.as_ref().map_or(0.0, |draws| draws[i]);
+        let win = val - draw / 2.0;
+        Wdl {
+            win,
+            draw,
+            loss: 1.0 - win - draw,
+        }
+    }
+}
+
+pub fn solve(rules: &RuleSet) -> Solution {
+    let (states, perma_keys) = get_order(rules);
+    let solution = solve_states(rules, states, &perma_keys);
+
+    save_vals(rules, &solution.vals, 0);
+    if let Some(draws) = &solution.draws {
+        println!("saving draws...");
+        save::write(&draws_path(rules), draws);
+    }
+    solution
+}
+
+/// Solves every state reachable from any of `starts`, for starts the usual table can't reach.
+/// Nothing is read from or saved to `./data`, as the table depends on the starts.
+pub fn solve_from(rules: &RuleSet, starts: &[GameState]) -> Solution {
+    let states = create_order_from(rules, starts);
+    let perma_keys = perma_ranges(rules, &states);
+    solve_states(rules, states, &perma_keys)
+}
+
+/// converges the values of states sorted in solving order, without reading or writing files
+fn solve_states(
+    rules: &RuleSet,
+    states: Vec<GameStateSmall>,
+    perma_keys: &[(PermaKey, Range<usize>)],
+) -> Solution {
     println!("number of states: {}", states.len());
     println!("number of perma keys: {}", perma_keys.len());
 
     let mut vals = vec![-1.0; states.len()];
+    let mut draws = rules.turn_limit.map(|_| vec![0.0; states.len()]);
 
-    let mut device_holder = DeviceHolder::new();
+    // only created once a range is big enough to be worth converging on the gpu
+    let mut device_holder: Option<DeviceHolder> = None;
 
     let mut expr_parts = Vec::new();
     let mut expr_starts = Vec::new();
@@ -42,8 +117,7 @@ pub fn solve() -> (Vec<GameStateSmall>, Vec<f64>) {
             let lowest_dep = perma_keys[..=i]
                 .iter()
                 .rev()
-                .filter(|(other_key, _)| other_key.reachable_in_one_move_from(*key))
-                .next_back()
+                .rfind(|(other_key, _)| other_key.reachable_in_one_move_from(*key))
                 .unwrap();
             let dep_start = lowest_dep.1.start;
             println!(
@@ -54,6 +128,7 @@ pub fn solve() -> (Vec<GameStateSmall>, Vec<f64>) {
                 key.team_lt.score
             );
             converge(
+                rules,
                 &states,
                 &mut vals,
                 dep_start,
@@ -63,18 +138,137 @@ pub fn solve() -> (Vec<GameStateSmall>, Vec<f64>) {
                 &mut expr_parts,
                 &mut expr_starts,
             );
+            if let Some(draws) = &mut draws {
+                converge_draws(
+                    rules,
+                    &vals,
+                    draws,
+                    dep_start,
+                    range.start,
+                    range.end,
+                    &expr_parts,
+                    &expr_starts,
+                );
+            }
         }
     });
 
-    save_vals(&vals, 0);
+    Solution {
+        states,
+        vals,
+        draws,
+    }
+}
 
-    (states, vals)
+/// loads the table saved by an earlier `solve` with these rules from `./data`, without solving
+/// anything
+pub fn load(rules: &RuleSet) -> Option<Solution> {
+    let draws = draws_path(rules);
+    load_files(
+        rules,
+        Path::new(&order_path(rules)),
+        Path::new(&vals_path(rules, 0)),
+        rules.turn_limit.map(|_| Path::new(&draws)),
+    )
 }
 
-pub fn save_vals(vals: &[f64], converge_count: usize) {
-    println!("saving vals...");
-    save::write(
-        &format!("./data/vals_{GOAL_SCORE}_{converge_count}.bin"),
+/// Loads a table from its order, vals and, with a turn limit, draws files, such as the published
+/// `order_7.bin` and `vals_7.bin`. Tables saved in a different order, like the published ones
+/// whose states are 32 bit, are sorted into solving order.
+pub fn load_files(
+    rules: &RuleSet,
+    order: &Path,
+    vals: &Path,
+    draws: Option<&Path>,
+) -> Option<Solution> {
+    // bincode writes integers as varints, so 32 bit states read back as 64 bit ones
+    let states: Vec<u64> = save::read(order)?;
+    let mut states: Vec<GameStateSmall> = states.into_iter().map(GameStateSmall::from).collect();
+    let mut vals: Vec<f64> = save::read(vals)?;
+    let mut draws: Option<Vec<f64>> = match (rules.turn_limit, draws) {
+        (Some(_), Some(draws)) => Some(save::read(draws)?),
+        (Some(_), None) => return None,
+        (None, _) => None,
+    };
+    let complete = vals.len() == states.len()
+        && draws
+            .as_ref()
+            .is_none_or(|draws| draws.len() == states.len());
+    if !complete {
+        return None;
+    }
+    if !states.is_sorted_by(|x, y| cmp_states(rules, x, y).is_lt()) {
+        println!("sorting...");
+        let mut order = (0..states.len()).collect::<Vec<_>>();
+        order.par_sort_by(|&x, &y| cmp_states(rules, &states[x], &states[y]));
+        states = order.iter().map(|&i| states[i]).collect();
+        vals = order.iter().map(|&i| vals[i]).collect();
+        draws = draws.map(|draws| order.iter().map(|&i| draws[i]).collect());
+    }
+    Some(Solution {
+        states,
         vals,
-    );
+        draws,
+    })
+}
+
+/// loads the saved table if there is one, and solves it otherwise
+pub fn load_or_solve(rules: &RuleSet) -> Solution {
+    load(rules).unwrap_or_else(|| solve(rules))
+}
+
+fn vals_path(rules: &RuleSet, converge_count: usize) -> String {
+    format!("./data/vals_{}_{converge_count}.bin", rules.key())
+}
+
+fn draws_path(rules: &RuleSet) -> String {
+    format!("./data/draws_{}_0.bin", rules.key())
+}
+
+pub fn save_vals(rules: &RuleSet, vals: &[f64], converge_count: usize) {
+    println!("saving vals...");
+    save::write(&vals_path(rules, converge_count), vals);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::dice::DiceModel;
+
+    /// every value is the sum of its roll terms once converged, which only holds if each
+    /// state's expression is read from where it starts
+    fn assert_vals_match_roll_terms(rules: &RuleSet) {
+        let solution = solve_from(rules, &[GameState::new()]);
+        for (state, val) in solution.states.iter().zip(&solution.vals) {
+            let game = state.unpack(&rules.board);
+            let terms = solution.roll_terms(rules, game).unwrap();
+            let sum: f64 = terms.iter().map(|term| term.contribution()).sum();
+            assert!(
+                (sum - val).abs() < 1e-9,
+                "{game:?} is {val}, its rolls sum to {sum}"
+            );
+        }
+    }
+
+    #[test]
+    fn vals_match_roll_terms() {
+        assert_vals_match_roll_terms(&RuleSet::with_pieces(2).unwrap());
+    }
+
+    // without a 0 roll the first roll of a state can have several moves
+    #[test]
+    fn vals_match_roll_terms_without_zero() {
+        let rules = RuleSet::with_pieces(2)
+            .unwrap()
+            .with_dice(DiceModel::binary_zero_max(3).unwrap());
+        assert_vals_match_roll_terms(&rules);
+    }
+
+    #[test]
+    fn vals_match_roll_terms_on_masters() {
+        assert_vals_match_roll_terms(&RuleSet {
+            pieces: 2,
+            ..RuleSet::masters()
+        });
+    }
 }
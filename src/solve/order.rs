@@ -1,4 +1,5 @@
 use std::{
+    cmp::Ordering,
     collections::{BTreeMap, HashSet},
     ops::Range,
 };
@@ -6,64 +7,121 @@ use std::{
 use itertools::Itertools;
 
 use crate::{
-    game::{GameState, GameStateSmall, Move, PossibleMovesIter, Roll, GOAL_SCORE},
+    game::{rules::RuleSet, GameState, GameStateSmall, Move, PossibleMovesIter},
     save::{self, read_or_create},
     solve::perma::PermaKey,
-    successor::Succ,
 };
 use rayon::prelude::*;
 
-pub fn get_order() -> (Vec<GameStateSmall>, Vec<(PermaKey, Range<usize>)>) {
+pub fn get_order(rules: &RuleSet) -> (Vec<GameStateSmall>, Vec<(PermaKey, Range<usize>)>) {
     let mut states = read_or_create(
-        &format!("./data/order_{GOAL_SCORE}.bin"),
-        create_order,
+        &order_path(rules),
+        || create_order(rules),
         |states| states.iter().map(|state| (*state).into()).collect(),
-        |data: &Vec<u32>| data.iter().cloned().map(GameStateSmall::from).collect(),
+        |data: &Vec<u64>| data.iter().cloned().map(GameStateSmall::from).collect(),
     );
 
+    let perma_keys = perma_ranges(rules, &states);
+
+    let is_sorted = states.is_sorted_by(|x, y| cmp_states(rules, x, y).is_lt());
+    println!("is sorted: {is_sorted}");
+    if !is_sorted {
+        println!("sorting...");
+        sort_states(rules, &mut states);
+        save::write(
+            &order_path(rules),
+            states.iter().map(|state| u64::from(*state)).collect_vec(),
+        );
+    }
+
+    (states, perma_keys)
+}
+
+/// the range of states with each perma key, in solving order
+pub fn perma_ranges(rules: &RuleSet, states: &[GameStateSmall]) -> Vec<(PermaKey, Range<usize>)> {
     let mut counts: BTreeMap<PermaKey, usize> = BTreeMap::new();
-    for game in &states {
-        let key = PermaKey::from(game);
+    for game in states {
+        let key = PermaKey::new(game.unpack(&rules.board), rules);
         *counts.entry(key).or_insert(0) += 1;
     }
 
-    let perma_keys = counts.into_iter().sorted().fold(
+    counts.into_iter().sorted().fold(
         Vec::new(),
         |mut acc: Vec<(PermaKey, Range<usize>)>, (key, count)| {
             let start = acc.last().map(|(_, range)| range.end).unwrap_or(0);
             acc.push((key, start..(start + count)));
             acc
         },
-    );
+    )
+}
 
-    let is_sorted = states.is_sorted_by(|x, y| x < y);
-    println!("is sorted: {is_sorted}");
-    if !is_sorted {
-        println!("sorting...");
-        states.par_sort();
-        save::write(
-            &format!("./data/order_{GOAL_SCORE}.bin"),
-            states.iter().map(|state| u32::from(*state)).collect_vec(),
-        );
-    }
+pub(crate) fn order_path(rules: &RuleSet) -> String {
+    format!("./data/order_{}.bin", rules.key())
+}
 
-    (states, perma_keys)
+/// states are solved in order of their perma key, ties are broken by the packed bits
+pub fn state_key(rules: &RuleSet, state: GameStateSmall) -> (PermaKey, u64) {
+    (
+        PermaKey::new(state.unpack(&rules.board), rules),
+        state.into(),
+    )
 }
-pub fn create_order() -> Vec<GameStateSmall> {
+
+pub fn cmp_states(rules: &RuleSet, a: &GameStateSmall, b: &GameStateSmall) -> Ordering {
+    state_key(rules, *a).cmp(&state_key(rules, *b))
+}
+
+pub fn sort_states(rules: &RuleSet, states: &mut [GameStateSmall]) {
+    states.par_sort_by_cached_key(|state| state_key(rules, *state));
+}
+
+pub fn create_order(rules: &RuleSet) -> Vec<GameStateSmall> {
+    create_order_from(rules, &[GameState::new()])
+}
+
+/// every state reachable from any of `starts`, sorted in solving order
+pub fn create_order_from(rules: &RuleSet, starts: &[GameState]) -> Vec<GameStateSmall> {
     println!("creating order...");
     let mut states = HashSet::new();
-    let mut state_queue = vec![GameState::new().into()];
+    let mut state_queue: Vec<_> = starts
+        .iter()
+        .map(|&start| GameStateSmall::pack(start, &rules.board))
+        .collect();
 
     while let Some(game) = state_queue.pop() {
-        create_order_rec(game, &mut state_queue, &mut states);
+        create_order_rec(rules, game, &mut state_queue, &mut states);
     }
 
     let mut states: Vec<_> = states.into_iter().collect();
-    states.par_sort();
+    sort_states(rules, &mut states);
+    validate_order(rules, &states);
 
     states
 }
+
+/// checks that packing round trips and that every state only depends on states with the same
+/// or an earlier perma key, which `solve` relies on to converge one key at a time
+fn validate_order(rules: &RuleSet, states: &[GameStateSmall]) {
+    println!("validating order...");
+    states.par_iter().for_each(|state| {
+        let game = state.unpack(&rules.board);
+        assert_eq!(
+            GameStateSmall::pack(game, &rules.board),
+            *state,
+            "packing doesn't round trip for {game:?}"
+        );
+        let key = PermaKey::new(game, rules);
+        for dep in game_deps(rules, game) {
+            let dep_key = PermaKey::new(dep, rules);
+            assert!(
+                dep_key <= key && dep_key.reachable_in_one_move_from(key),
+                "{game:?} depends on {dep:?}, which is solved after it"
+            );
+        }
+    });
+}
 fn create_order_rec(
+    rules: &RuleSet,
     game: GameStateSmall,
     state_queue: &mut Vec<GameStateSmall>,
     states: &mut HashSet<GameStateSmall>,
@@ -73,24 +131,58 @@ fn create_order_rec(
     }
     states.insert(game);
 
-    if states.len() % 1_000_000 == 0 {
+    if states.len().is_multiple_of(1_000_000) {
         println!("created {} order", states.len());
     }
     state_queue.extend(
-        game_deps(GameState::from(game))
-            .map(GameStateSmall::from)
+        game_deps(rules, game.unpack(&rules.board))
+            .map(|new_game| GameStateSmall::pack(new_game, &rules.board))
             .filter(|new_game| !states.contains(new_game)),
     );
 }
 
-fn game_deps(game: GameState) -> impl Iterator<Item = GameState> {
-    Roll::succ_iter()
-        .flat_map(move |roll| PossibleMovesIter::new(game, roll))
+fn game_deps(rules: &RuleSet, game: GameState) -> impl Iterator<Item = GameState> + '_ {
+    rules
+        .dice
+        .rolls()
+        .flat_map(move |roll| PossibleMovesIter::new(rules, game, roll))
         .filter_map(|mov| {
-            if let Move::Continue { game, keep_turn } = mov {
+            if let Move::Continue { game, keep_turn } = mov.result {
                 Some(if keep_turn { game } else { game.flipped() })
             } else {
                 None
             }
         })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::board::Board;
+
+    // `create_order` runs `validate_order`, which panics if packing or the perma key order break
+    #[test]
+    fn masters_order_is_valid() {
+        let rules = RuleSet {
+            pieces: 2,
+            ..RuleSet::masters()
+        };
+        let states = create_order(&rules);
+        let start = GameStateSmall::pack(GameState::new(), &rules.board);
+        assert!(states.contains(&start));
+    }
+
+    // the private stretches pack into no bits at all, and on a 16 square track the end bits
+    // sit past the top of the strip
+    #[test]
+    fn boards_without_a_private_stretch_order_is_valid() {
+        for board in [
+            Board::new(16, 4, 16, &[3, 7]).unwrap(),
+            Board::new(12, 0, 10, &[2, 7]).unwrap(),
+        ] {
+            let rules = RuleSet::with_pieces(2).unwrap().with_board(board);
+            assert_eq!(board.end_len().min(board.start_len()), 0);
+            create_order(&rules);
+        }
+    }
+}
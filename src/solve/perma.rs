@@ -1,34 +1,44 @@
 use std::cmp::{self, Ordering};
 
-use crate::{
-    game::{
-        strip::{StripIndex, StripState},
-        GameState, GameStateSmall, TeamState,
-    },
-    successor::Succ,
+use crate::game::{
+    board::Board,
+    rules::RuleSet,
+    strip::{StripIndex, StripState},
+    GameState, TeamState,
 };
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
 pub struct PermaKey {
+    // later turns can't lead back to earlier ones, so they are solved first
+    pub turn: u8,
     pub team_gt: PermaTeamKey,
     pub team_lt: PermaTeamKey,
     max_token: MaxToken,
 }
 
 impl PermaKey {
-    pub fn new(game: GameState) -> Self {
-        let prot = (PermaTeamKey::new(game.prot), game.prot.strip.start_bits());
-        let opp = (PermaTeamKey::new(game.opp), game.opp.strip.start_bits());
+    pub fn new(game: GameState, rules: &RuleSet) -> Self {
+        let board = &rules.board;
+        let prot = (
+            PermaTeamKey::new(game.prot, board),
+            game.prot.strip.start_bits(board),
+        );
+        let opp = (
+            PermaTeamKey::new(game.opp, board),
+            game.opp.strip.start_bits(board),
+        );
         let ((team_gt, strip_start_gt), (team_lt, strip_start_lt)) =
             if prot > opp { (prot, opp) } else { (opp, prot) };
 
         let max_token = MaxToken::new(
+            rules,
             game.prot.strip,
             game.opp.strip,
             strip_start_gt,
             strip_start_lt,
         );
         PermaKey {
+            turn: game.turn,
             team_gt,
             team_lt,
             max_token,
@@ -36,36 +46,14 @@ impl PermaKey {
     }
 
     pub fn reachable_in_one_move_from(&self, other: PermaKey) -> bool {
-        match (
-            self.team_gt.score.checked_sub(other.team_gt.score),
-            self.team_lt.score.checked_sub(other.team_lt.score),
-        ) {
-            (Some(0), Some(0)) | (Some(1), Some(0)) | (Some(0), Some(1)) => true,
-            _ => false,
-        }
-    }
-}
-
-impl From<GameState> for PermaKey {
-    fn from(value: GameState) -> Self {
-        Self::new(value)
-    }
-}
-
-impl From<&GameState> for PermaKey {
-    fn from(value: &GameState) -> Self {
-        Self::new(*value)
-    }
-}
-
-impl From<GameStateSmall> for PermaKey {
-    fn from(value: GameStateSmall) -> Self {
-        Self::new(GameState::from(value))
-    }
-}
-impl From<&GameStateSmall> for PermaKey {
-    fn from(value: &GameStateSmall) -> Self {
-        Self::new(GameState::from(*value))
+        matches!(self.turn.checked_sub(other.turn), Some(0 | 1))
+            && matches!(
+                (
+                    self.team_gt.score.checked_sub(other.team_gt.score),
+                    self.team_lt.score.checked_sub(other.team_lt.score),
+                ),
+                (Some(0), Some(0)) | (Some(1), Some(0)) | (Some(0), Some(1))
+            )
     }
 }
 
@@ -78,6 +66,7 @@ impl PartialOrd for PermaKey {
 impl Ord for PermaKey {
     fn cmp(&self, other: &Self) -> cmp::Ordering {
         (
+            self.turn,
             self.team_gt.score,
             self.team_gt.strip_end,
             self.team_lt.score,
@@ -85,6 +74,7 @@ impl Ord for PermaKey {
             self.max_token,
         )
             .cmp(&(
+                other.turn,
                 other.team_gt.score,
                 other.team_gt.strip_end,
                 other.team_lt.score,
@@ -98,47 +88,57 @@ impl Ord for PermaKey {
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
 pub struct MaxToken {
     focus_token: FocusToken,
-    max_is_flower: bool,
+    // set when the furthest shared piece sits on a safe rosette, which can't be captured, so
+    // the pieces behind it can be used to split the key further
+    max_flower: Option<StripIndex>,
 }
 
 impl MaxToken {
     pub fn new(
+        rules: &RuleSet,
         prot_strip: StripState,
         opp_strip: StripState,
         strip_start_gt: u8,
         strip_start_lt: u8,
     ) -> Self {
-        let index = StripIndex::succ_iter()
-            .take(12)
-            .filter(|i| prot_strip.get(*i) || opp_strip.get(*i))
-            .last();
-        match index {
-            None | Some(StripIndex(..4)) => MaxToken {
+        let board = &rules.board;
+        // a piece on a loop can be captured by one with a lower index, so the furthest piece
+        // can go back and the states are only split by score
+        if board.loop_len() > 0 {
+            return MaxToken {
                 focus_token: FocusToken::Start {
-                    strip_start_gt,
-                    strip_start_lt,
+                    strip_start_gt: 0,
+                    strip_start_lt: 0,
                 },
-                max_is_flower: false,
-            },
-            Some(StripIndex(7)) => {
-                let second_index = StripIndex::succ_iter()
-                    .take(7)
-                    .filter(|i| prot_strip.get(*i) || opp_strip.get(*i))
-                    .last();
+                max_flower: None,
+            };
+        }
+        let start = FocusToken::Start {
+            strip_start_gt,
+            strip_start_lt,
+        };
+        let furthest_below = |end: StripIndex| {
+            board
+                .indices()
+                .take_while(|i| *i < end)
+                .filter(|i| prot_strip.get(*i) || opp_strip.get(*i))
+                .last()
+        };
+        let shared_focus = |index: Option<StripIndex>| match index {
+            Some(i) if board.is_shared(i) => FocusToken::Shared(i),
+            _ => start,
+        };
+        let index = furthest_below(StripIndex(board.start_len() + board.shared_len()));
+        match index {
+            Some(i) if rules.safe_rosettes && board.is_shared(i) && board.is_rosette(i) => {
                 MaxToken {
-                    focus_token: match second_index {
-                        None | Some(StripIndex(..4)) => FocusToken::Start {
-                            strip_start_gt,
-                            strip_start_lt,
-                        },
-                        Some(i) => FocusToken::Shared(i),
-                    },
-                    max_is_flower: true,
+                    focus_token: shared_focus(furthest_below(i)),
+                    max_flower: Some(i),
                 }
             }
-            Some(i) => MaxToken {
-                focus_token: FocusToken::Shared(i),
-                max_is_flower: false,
+            index => MaxToken {
+                focus_token: shared_focus(index),
+                max_flower: None,
             },
         }
     }
@@ -180,11 +180,13 @@ impl Ord for FocusToken {
 
 impl Ord for MaxToken {
     fn cmp(&self, other: &Self) -> Ordering {
-        match (self.max_is_flower, other.max_is_flower) {
-            (true, true) => self.focus_token.cmp(&other.focus_token),
-            (false, false) => self.focus_token.cmp(&other.focus_token),
-            (true, false) => FocusToken::Shared(StripIndex(7)).cmp(&other.focus_token),
-            (false, true) => self.focus_token.cmp(&FocusToken::Shared(StripIndex(7))),
+        match (self.max_flower, other.max_flower) {
+            (Some(i), Some(j)) => i
+                .cmp(&j)
+                .then_with(|| self.focus_token.cmp(&other.focus_token)),
+            (None, None) => self.focus_token.cmp(&other.focus_token),
+            (Some(i), None) => FocusToken::Shared(i).cmp(&other.focus_token),
+            (None, Some(j)) => self.focus_token.cmp(&FocusToken::Shared(j)),
         }
     }
 }
@@ -202,8 +204,8 @@ pub struct PermaTeamKey {
 }
 
 impl PermaTeamKey {
-    pub fn new(team: TeamState) -> Self {
-        let strip_end = team.strip.end_bits();
+    pub fn new(team: TeamState, board: &Board) -> Self {
+        let strip_end = team.strip.end_bits(board);
         Self {
             score: team.score,
             strip_end,
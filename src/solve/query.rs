@@ -0,0 +1,75 @@
+use crate::{
+    game::{rules::RuleSet, GameState, Move, MoveRecord, PossibleMovesIter, Roll},
+    solve::Solution,
+};
+
+/// a legal move and its value for the player making it
+#[derive(Debug, Clone)]
+pub struct RankedMove {
+    pub mov: MoveRecord,
+    pub val: f64,
+}
+
+/// one of the terms `eval_expr` sums for a state
+#[derive(Debug, Clone, Copy)]
+pub struct RollTerm {
+    pub roll: Roll,
+    pub prob: f64,
+    /// the value of the best move with this roll
+    pub best: f64,
+}
+
+impl RollTerm {
+    pub fn contribution(&self) -> f64 {
+        self.prob * self.best
+    }
+}
+
+impl Solution {
+    /// the value of a move for the player making it, flipping and inverting when the turn
+    /// passes, like `eval_expr` does
+    pub fn move_val(&self, rules: &RuleSet, mov: &Move) -> Option<f64> {
+        match mov {
+            Move::End(outcome) => Some(outcome.val()),
+            Move::Continue {
+                game,
+                keep_turn: true,
+            } => self.lookup(rules, *game),
+            Move::Continue {
+                game,
+                keep_turn: false,
+            } => self.lookup(rules, game.flipped()).map(|val| 1.0 - val),
+        }
+    }
+
+    /// every legal move with this roll, best first. None if a resulting state isn't solved
+    pub fn ranked_moves(
+        &self,
+        rules: &RuleSet,
+        game: GameState,
+        roll: Roll,
+    ) -> Option<Vec<RankedMove>> {
+        let mut moves = PossibleMovesIter::new(rules, game, roll)
+            .map(|mov| {
+                let val = self.move_val(rules, &mov.result)?;
+                Some(RankedMove { mov, val })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        moves.sort_by(|a, b| b.val.total_cmp(&a.val));
+        Some(moves)
+    }
+
+    /// every roll with its probability and best move value, whose contributions add up to the
+    /// value of the state
+    pub fn roll_terms(&self, rules: &RuleSet, game: GameState) -> Option<Vec<RollTerm>> {
+        rules
+            .dice
+            .rolls()
+            .zip(rules.dice.weights())
+            .map(|(roll, prob)| {
+                let best = self.ranked_moves(rules, game, roll)?[0].val;
+                Some(RollTerm { roll, prob, best })
+            })
+            .collect()
+    }
+}
@@ -1,4 +1,3 @@
-
 pub trait Succ {
     fn succ(&self) -> Option<Self>
     where